@@ -0,0 +1,87 @@
+use proc_macro2::Span;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Attribute, Expr, Ident, Token,
+};
+
+/// A single argument of a helper attribute, either `key = expr` or a bare `flag`.
+pub struct Arg {
+    name: Ident,
+    value: Option<Expr>,
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self { name, value })
+    }
+}
+
+/// Arguments of all `#[$attribute(...)]` helper attributes in `attrs`.
+pub struct Args {
+    span: Span,
+    args: Vec<Arg>,
+}
+
+impl Args {
+    pub fn parse(attrs: &[Attribute], attribute: &str, span: Span) -> syn::Result<Self> {
+        let mut args = Vec::new();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident(attribute)) {
+            args.extend(attr.parse_args_with(Punctuated::<Arg, Token![,]>::parse_terminated)?);
+        }
+
+        Ok(Self { span, args })
+    }
+
+    /// Fail on any argument not in `known`, to catch typos.
+    pub fn check(&self, known: &[&str]) -> syn::Result<()> {
+        match self
+            .args
+            .iter()
+            .find(|arg| !known.iter().any(|name| arg.name == name))
+        {
+            Some(arg) => Err(syn::Error::new(
+                arg.name.span(),
+                format!("unknown argument `{}`", arg.name),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn flag(&self, name: &str) -> syn::Result<bool> {
+        match self.args.iter().find(|arg| arg.name == name) {
+            Some(Arg { value: None, .. }) => Ok(true),
+            Some(Arg { name, .. }) => Err(syn::Error::new(
+                name.span(),
+                format!("`{}` does not take a value", name),
+            )),
+            None => Ok(false),
+        }
+    }
+
+    pub fn value(&self, name: &str) -> syn::Result<Option<&Expr>> {
+        match self.args.iter().find(|arg| arg.name == name) {
+            Some(Arg {
+                value: Some(value), ..
+            }) => Ok(Some(value)),
+            Some(Arg { name, .. }) => Err(syn::Error::new(
+                name.span(),
+                format!("`{}` needs a value", name),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    pub fn required(&self, name: &str) -> syn::Result<&Expr> {
+        self.value(name)?
+            .ok_or_else(|| syn::Error::new(self.span, format!("missing `{}` argument", name)))
+    }
+}
@@ -0,0 +1,364 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{spanned::Spanned, Data, DeriveInput, Error, Expr, Fields, Ident, Type};
+
+use crate::args::Args;
+
+struct Variant<'a> {
+    ident: &'a Ident,
+    command: Expr,
+    version: Expr,
+    field: Option<&'a Type>,
+    parse: bool,
+}
+
+impl<'a> Variant<'a> {
+    fn new(variant: &'a syn::Variant, version: Option<&Expr>) -> syn::Result<Self> {
+        let args = Args::parse(&variant.attrs, "genl", variant.span())?;
+        args.check(&["cmd", "version", "parse"])?;
+
+        let field = match &variant.fields {
+            Fields::Unit => None,
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Some(&fields.unnamed[0].ty),
+            fields => {
+                return Err(Error::new(
+                    fields.span(),
+                    "messages must be unit variants or wrap a single set of attributes",
+                ))
+            }
+        };
+
+        Ok(Self {
+            ident: &variant.ident,
+            command: args.required("cmd")?.clone(),
+            version: args.value("version")?.or(version).cloned().ok_or_else(|| {
+                Error::new(
+                    variant.span(),
+                    "missing `version`, either on the variant or the enum",
+                )
+            })?,
+            field,
+            parse: args.flag("parse")?,
+        })
+    }
+
+    fn attribute_size(&self) -> TokenStream {
+        let ident = self.ident;
+        match self.field {
+            Some(_) => quote! {
+                Self::#ident(attributes) => {
+                    ::netlink_packet_utils::traits::Emitable::buffer_len(attributes)
+                }
+            },
+            None => quote!(Self::#ident => 0),
+        }
+    }
+
+    fn emit_attributes(&self) -> TokenStream {
+        let ident = self.ident;
+        match self.field {
+            Some(_) => quote! {
+                Self::#ident(attributes) => {
+                    ::netlink_packet_utils::traits::Emitable::emit(attributes, buffer)
+                }
+            },
+            None => quote!(Self::#ident => {}),
+        }
+    }
+
+    fn parse(&self) -> TokenStream {
+        let Self { ident, command, .. } = self;
+        let message = match self.field {
+            Some(ty) => quote! {
+                Self::#ident(
+                    <#ty as ::netlink_packet_utils::traits::Parseable<&[u8]>>::parse(
+                        &buffer.attributes(),
+                    )?,
+                )
+            },
+            None => quote!(Self::#ident),
+        };
+
+        quote!(command if command == #command => Ok(#message))
+    }
+}
+
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => return Err(Error::new(name.span(), "GenlMessage only supports enums")),
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "GenlMessage does not support generic enums",
+        ));
+    }
+
+    let args = Args::parse(&input.attrs, "genl", name.span())?;
+    args.check(&["version"])?;
+
+    let variants = data
+        .variants
+        .iter()
+        .map(|variant| Variant::new(variant, args.value("version")?))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let idents = variants
+        .iter()
+        .map(|variant| variant.ident)
+        .collect::<Vec<_>>();
+    let commands = variants.iter().map(|variant| &variant.command);
+    let versions = variants.iter().map(|variant| &variant.version);
+    let sizes = variants.iter().map(Variant::attribute_size);
+    let emits = variants.iter().map(Variant::emit_attributes);
+    let parsed = variants
+        .iter()
+        .filter(|variant| variant.parse)
+        .collect::<Vec<_>>();
+    for (index, variant) in parsed.iter().enumerate() {
+        let command = variant.command.to_token_stream().to_string();
+        if parsed[..index]
+            .iter()
+            .any(|earlier| earlier.command.to_token_stream().to_string() == command)
+        {
+            return Err(Error::new(
+                variant.ident.span(),
+                "another `parse` variant already has this `cmd`",
+            ));
+        }
+    }
+    // Distinct constants can still share a value, which only the compiler can tell
+    let parsed_commands = parsed.iter().map(|variant| &variant.command);
+    let duplicate = format!("two `parse` variants of {} share a command", name);
+    let parsed = parsed.iter().map(|variant| variant.parse());
+
+    Ok(quote! {
+        const _: () = {
+            let commands: &[u8] = &[#(#parsed_commands),*];
+            let mut index = 0;
+            while index < commands.len() {
+                let mut other = index + 1;
+                while other < commands.len() {
+                    if commands[index] == commands[other] {
+                        panic!(#duplicate);
+                    }
+                    other += 1;
+                }
+                index += 1;
+            }
+        };
+
+        impl crate::genl::GenlPayload for #name {
+            fn command(&self) -> u8 {
+                match self {
+                    #(Self::#idents { .. } => #commands,)*
+                }
+            }
+
+            fn version(&self) -> u8 {
+                match self {
+                    #(Self::#idents { .. } => #versions,)*
+                }
+            }
+
+            fn attribute_size(&self) -> usize {
+                match self {
+                    #(#sizes,)*
+                }
+            }
+
+            fn emit_attributes(&self, buffer: &mut [u8]) {
+                match self {
+                    #(#emits,)*
+                }
+            }
+
+            fn parse(
+                buffer: &crate::genl::GenericBuffer<&[u8]>,
+            ) -> Result<Self, ::netlink_packet_utils::DecodeError> {
+                match buffer.command() {
+                    #(#parsed,)*
+                    command => Err(format!("unsupported command {}", command).into()),
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        match derive(input) {
+            Ok(tokens) => panic!("expected an error, got {}", tokens),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_only_marked_variants() {
+        let expanded = derive(parse_quote! {
+            #[genl(version = 1)]
+            enum Message {
+                #[genl(cmd = CMD_NEW, version = 2, parse)]
+                New(Attributes),
+                #[genl(cmd = CMD_GET)]
+                Get(Attributes),
+                #[genl(cmd = CMD_FLUSH)]
+                Flush,
+            }
+        })
+        .unwrap()
+        .to_string();
+
+        let function = |name: &str| {
+            let start = expanded.find(&format!("fn {}", name)).unwrap();
+            let end = expanded[start + 3..]
+                .find("fn ")
+                .map_or(expanded.len(), |end| start + 3 + end);
+            &expanded[start..end]
+        };
+
+        let parse = function("parse");
+        assert!(parse.contains("command == CMD_NEW"));
+        assert!(!parse.contains("CMD_GET"));
+        assert!(!parse.contains("CMD_FLUSH"));
+
+        // The enum's version applies unless a variant has its own
+        let version = function("version");
+        assert!(version.contains("Self :: New { .. } => 2"));
+        assert!(version.contains("Self :: Get { .. } => 1"));
+        assert!(function("attribute_size").contains("Self :: Flush => 0"));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(
+            error(parse_quote! {
+                #[genl(version = 1)]
+                enum Message {
+                    #[genl(command = CMD_GET)]
+                    Get,
+                }
+            }),
+            "unknown argument `command`"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[genl(version = 1)]
+                enum Message {
+                    #[genl(parse)]
+                    Get,
+                }
+            }),
+            "missing `cmd` argument"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Message {
+                    #[genl(cmd = CMD_GET)]
+                    Get,
+                }
+            }),
+            "missing `version`, either on the variant or the enum"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[genl(version = 1)]
+                enum Message {
+                    #[genl(cmd = CMD_GET, parse = true)]
+                    Get,
+                }
+            }),
+            "`parse` does not take a value"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[genl(version)]
+                enum Message {
+                    #[genl(cmd = CMD_GET)]
+                    Get,
+                }
+            }),
+            "`version` needs a value"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[genl(version = 1, cmd = CMD_GET)]
+                enum Message {
+                    Get,
+                }
+            }),
+            "unknown argument `cmd`"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[genl(version = 1)]
+                enum Message {
+                    #[genl(cmd = CMD_NEW, parse)]
+                    New(Attributes),
+                    #[genl(cmd = CMD_NEW)]
+                    Set(Attributes),
+                    #[genl(cmd = CMD_NEW, parse)]
+                    Notify(Attributes),
+                }
+            }),
+            "another `parse` variant already has this `cmd`"
+        );
+    }
+
+    #[test]
+    fn checks_parsed_commands_are_distinct() {
+        let expanded = derive(parse_quote! {
+            #[genl(version = 1)]
+            enum Message {
+                #[genl(cmd = CMD_NEW, parse)]
+                New(Attributes),
+                #[genl(cmd = CMD_GET)]
+                Get,
+                #[genl(cmd = CMD_DEL, parse)]
+                Del,
+            }
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(expanded.contains("let commands : & [u8] = & [CMD_NEW , CMD_DEL]"));
+        assert!(expanded.contains("panic ! (\"two `parse` variants of Message share a command\")"));
+    }
+
+    #[test]
+    fn rejects_unsupported_items() {
+        assert_eq!(
+            error(parse_quote! {
+                #[genl(version = 1)]
+                enum Message {
+                    #[genl(cmd = CMD_GET)]
+                    Get(Index, Name),
+                }
+            }),
+            "messages must be unit variants or wrap a single set of attributes"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                struct Message;
+            }),
+            "GenlMessage only supports enums"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[genl(version = 1)]
+                enum Message<T> {
+                    #[genl(cmd = CMD_GET)]
+                    Get(T),
+                }
+            }),
+            "GenlMessage does not support generic enums"
+        );
+    }
+}
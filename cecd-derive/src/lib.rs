@@ -0,0 +1,67 @@
+//! Derive macros for generic netlink messages and attribute sets.
+//!
+//! The generated code refers to `crate::genl`, so these derives are only meant to be used from
+//! within `cecd`.
+
+extern crate proc_macro;
+
+mod args;
+mod genl_message;
+mod nla_set;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Derive `GenlPayload` for an enum of generic netlink messages.
+///
+/// Each variant is either a unit variant without attributes or wraps a single value implementing
+/// `Emitable`, and is annotated with `#[genl(cmd = ..)]`. The header version is given per variant
+/// or for the whole enum with `#[genl(version = ..)]`.
+///
+/// Variants marked with `parse` are what a message with their command is parsed into. Their
+/// value is parsed from the attributes with `Parseable<&[u8]>`, messages with any other command
+/// fail to parse.
+///
+/// ```ignore
+/// #[derive(GenlMessage)]
+/// #[genl(version = 1)]
+/// pub enum ControlMessage {
+///     #[genl(cmd = CTRL_CMD_NEWFAMILY, version = 2, parse)]
+///     NewFamily(NewFamily),
+///     #[genl(cmd = CTRL_CMD_GETFAMILY)]
+///     GetFamily(FamilyName),
+/// }
+/// ```
+#[proc_macro_derive(GenlMessage, attributes(genl))]
+pub fn derive_genl_message(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    genl_message::derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Derive `Nla` and `Parseable<NlaBuffer>` for an enum with one variant per attribute.
+///
+/// Variants are annotated with `#[nla(kind = ..)]`. Unit variants are flags, single-value
+/// variants hold any `AttributeValue` and are emitted with `NLA_F_NESTED` if it is nested. A
+/// variant `Other(u16, Vec<u8>)` marked `#[nla(unknown)]` collects attributes of any other kind,
+/// without one they fail to parse.
+///
+/// ```ignore
+/// #[derive(NlaSet)]
+/// pub enum MonitorFlag {
+///     #[nla(kind = NL80211_MNTR_FLAG_CONTROL)]
+///     Control,
+///     #[nla(kind = NL80211_MNTR_FLAG_OTHER_BSS)]
+///     OtherBss,
+///     #[nla(unknown)]
+///     Other(u16, Vec<u8>),
+/// }
+/// ```
+#[proc_macro_derive(NlaSet, attributes(nla))]
+pub fn derive_nla_set(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    nla_set::derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
@@ -0,0 +1,351 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{spanned::Spanned, Data, DeriveInput, Error, Fields, Ident, Type};
+
+use crate::args::Args;
+
+enum Shape<'a> {
+    /// A zero-length flag attribute.
+    Flag,
+    /// An attribute carrying a single `AttributeValue` of the given type.
+    Value(&'a Type),
+    /// The catch-all for attribute kinds not listed, holding the kind and the raw value.
+    Unknown,
+}
+
+struct Variant<'a> {
+    ident: &'a Ident,
+    kind: TokenStream,
+    shape: Shape<'a>,
+}
+
+impl<'a> Variant<'a> {
+    fn new(variant: &'a syn::Variant) -> syn::Result<Self> {
+        let args = Args::parse(&variant.attrs, "nla", variant.span())?;
+        args.check(&["kind", "unknown"])?;
+
+        if args.flag("unknown")? {
+            return match &variant.fields {
+                Fields::Unnamed(fields) if fields.unnamed.len() == 2 => Ok(Self {
+                    ident: &variant.ident,
+                    kind: TokenStream::new(),
+                    shape: Shape::Unknown,
+                }),
+                fields => Err(Error::new(
+                    fields.span(),
+                    "unknown attributes must hold their kind and value, e.g. `Other(u16, Vec<u8>)`",
+                )),
+            };
+        }
+
+        let shape = match &variant.fields {
+            Fields::Unit => Shape::Flag,
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Shape::Value(&fields.unnamed[0].ty)
+            }
+            fields => {
+                return Err(Error::new(
+                    fields.span(),
+                    "attributes must be unit variants or hold a single value",
+                ))
+            }
+        };
+
+        let kind = args.required("kind")?;
+        let kind = quote!(#kind);
+        Ok(Self {
+            ident: &variant.ident,
+            kind,
+            shape,
+        })
+    }
+
+    fn value_len(&self) -> TokenStream {
+        let ident = self.ident;
+        match self.shape {
+            Shape::Flag => quote!(Self::#ident => 0),
+            Shape::Value(_) => quote! {
+                Self::#ident(value) => crate::genl::AttributeValue::value_len(value)
+            },
+            Shape::Unknown => quote!(Self::#ident(_, value) => value.len()),
+        }
+    }
+
+    fn kind(&self) -> TokenStream {
+        let Self { ident, kind, .. } = self;
+        match self.shape {
+            Shape::Flag => quote!(Self::#ident => #kind),
+            // Sets `NLA_F_NESTED` for values made up of attributes, as `impl_wrapped_attribute!`
+            Shape::Value(ty) => quote! {
+                Self::#ident(_) => crate::genl::value_kind::<#ty>(#kind)
+            },
+            Shape::Unknown => quote!(Self::#ident(kind, _) => *kind),
+        }
+    }
+
+    fn emit_value(&self) -> TokenStream {
+        let ident = self.ident;
+        match self.shape {
+            Shape::Flag => quote!(Self::#ident => {}),
+            Shape::Value(_) => quote! {
+                Self::#ident(value) => crate::genl::AttributeValue::emit_value(value, buffer)
+            },
+            Shape::Unknown => quote! {
+                Self::#ident(_, value) => buffer.copy_from_slice(value)
+            },
+        }
+    }
+
+    fn parse(&self, name: &Ident) -> Option<TokenStream> {
+        let Self { ident, kind, .. } = self;
+        let guard = quote! {
+            kind if kind == (#kind) & ::netlink_packet_utils::nla::NLA_TYPE_MASK
+        };
+
+        match self.shape {
+            Shape::Flag => {
+                let message = format!("invalid flag {}::{}: {{:?}}", name, ident);
+                Some(quote! {
+                    #guard => match buffer.value() {
+                        [] => Ok(Self::#ident),
+                        value => Err(format!(#message, value).into()),
+                    }
+                })
+            }
+            Shape::Value(_) => Some(quote! {
+                #guard => crate::genl::AttributeValue::parse_value(buffer.value()).map(Self::#ident)
+            }),
+            Shape::Unknown => None,
+        }
+    }
+}
+
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => return Err(Error::new(name.span(), "NlaSet only supports enums")),
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "NlaSet does not support generic enums",
+        ));
+    }
+
+    let variants = data
+        .variants
+        .iter()
+        .map(Variant::new)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let mut unknown = variants
+        .iter()
+        .filter(|variant| matches!(variant.shape, Shape::Unknown));
+    let fallback = match (unknown.next(), unknown.next()) {
+        (Some(variant), None) => {
+            let ident = variant.ident;
+            quote!(kind => Ok(Self::#ident(kind, buffer.value().to_vec())))
+        }
+        (None, _) => {
+            let message = format!("encountered unexpected kind {{}} when parsing {}", name);
+            quote!(kind => Err(format!(#message, kind).into()))
+        }
+        (Some(_), Some(duplicate)) => {
+            return Err(Error::new(
+                duplicate.ident.span(),
+                "only one variant can collect unknown attributes",
+            ))
+        }
+    };
+
+    let value_lens = variants.iter().map(Variant::value_len);
+    let kinds = variants.iter().map(Variant::kind);
+    let emits = variants.iter().map(Variant::emit_value);
+    let parsed = variants.iter().filter_map(|variant| variant.parse(name));
+
+    Ok(quote! {
+        impl ::netlink_packet_utils::nla::Nla for #name {
+            fn value_len(&self) -> usize {
+                match self {
+                    #(#value_lens,)*
+                }
+            }
+
+            fn kind(&self) -> u16 {
+                match self {
+                    #(#kinds,)*
+                }
+            }
+
+            fn emit_value(&self, buffer: &mut [u8]) {
+                match self {
+                    #(#emits,)*
+                }
+            }
+        }
+
+        impl<'buffer, T: AsRef<[u8]> + ?Sized>
+            ::netlink_packet_utils::traits::Parseable<
+                ::netlink_packet_utils::nla::NlaBuffer<&'buffer T>,
+            > for #name
+        {
+            fn parse(
+                buffer: &::netlink_packet_utils::nla::NlaBuffer<&'buffer T>,
+            ) -> Result<Self, ::netlink_packet_utils::DecodeError> {
+                match buffer.kind() {
+                    #(#parsed,)*
+                    #fallback,
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        match derive(input) {
+            Ok(tokens) => panic!("expected an error, got {}", tokens),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn value_kind_follows_type() {
+        let expanded = derive(parse_quote! {
+            enum Attribute {
+                #[nla(kind = ATTR_FLAG)]
+                Flag,
+                #[nla(kind = ATTR_TABLE)]
+                Table(Table),
+            }
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(expanded.contains("Self :: Flag => ATTR_FLAG"));
+        assert!(expanded.contains(
+            "Self :: Table (_) => crate :: genl :: value_kind :: < Table > (ATTR_TABLE)"
+        ));
+        // Parsing masks `NLA_F_NESTED` off, the kernel does not always set it
+        assert!(expanded
+            .contains("kind == (ATTR_TABLE) & :: netlink_packet_utils :: nla :: NLA_TYPE_MASK"));
+        assert!(expanded.contains("when parsing Attribute"));
+    }
+
+    #[test]
+    fn unknown_collects_other_kinds() {
+        let expanded = derive(parse_quote! {
+            enum Attribute {
+                #[nla(kind = ATTR_FLAG)]
+                Flag,
+                #[nla(unknown)]
+                Other(u16, Vec<u8>),
+            }
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(expanded.contains("Self :: Other (kind , _) => * kind"));
+        assert!(
+            expanded.contains("kind => Ok (Self :: Other (kind , buffer . value () . to_vec ()))")
+        );
+        assert!(!expanded.contains("unexpected kind"));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(
+            error(parse_quote! {
+                enum Attribute {
+                    #[nla(kind = ATTR_FLAG, nest)]
+                    Flag,
+                }
+            }),
+            "unknown argument `nest`"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Attribute {
+                    #[nla(kind = ATTR_TABLE, nested)]
+                    Table(Table),
+                }
+            }),
+            "unknown argument `nested`"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Attribute {
+                    #[nla()]
+                    Table(Table),
+                }
+            }),
+            "missing `kind` argument"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Attribute {
+                    #[nla(unknown = true)]
+                    Other(u16, Vec<u8>),
+                }
+            }),
+            "`unknown` does not take a value"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Attribute {
+                    #[nla(kind)]
+                    Flag,
+                }
+            }),
+            "`kind` needs a value"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Attribute {
+                    #[nla(kind = ATTR_FLAG)]
+                    Flag { set: bool },
+                }
+            }),
+            "attributes must be unit variants or hold a single value"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_unknown_variants() {
+        assert_eq!(
+            error(parse_quote! {
+                enum Attribute {
+                    #[nla(unknown)]
+                    Other(Vec<u8>),
+                }
+            }),
+            "unknown attributes must hold their kind and value, e.g. `Other(u16, Vec<u8>)`"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Attribute {
+                    #[nla(unknown)]
+                    Other(u16, Vec<u8>),
+                    #[nla(unknown)]
+                    Unparsed(u16, Vec<u8>),
+                }
+            }),
+            "only one variant can collect unknown attributes"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Attribute<T> {
+                    #[nla(kind = ATTR_VALUE)]
+                    Value(T),
+                }
+            }),
+            "NlaSet does not support generic enums"
+        );
+    }
+}
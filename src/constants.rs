@@ -1,10 +0,0 @@
-pub(crate) const CTRL_CMD_UNSPEC: u8 = 0;
-pub(crate) const CTRL_CMD_NEWFAMILY: u8 = 1;
-pub(crate) const CTRL_CMD_DELFAMILY: u8 = 2;
-pub(crate) const CTRL_CMD_GETFAMILY: u8 = 3;
-pub(crate) const CTRL_CMD_NEWOPS: u8 = 4;
-pub(crate) const CTRL_CMD_DELOPS: u8 = 5;
-pub(crate) const CTRL_CMD_GETOPS: u8 = 6;
-pub(crate) const CTRL_CMD_NEWMCAST_GRP: u8 = 7;
-pub(crate) const CTRL_CMD_DELMCAST_GRP: u8 = 8;
-pub(crate) const CTRL_CMD_GETMCAST_GRP: u8 = 9;
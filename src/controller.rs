@@ -1,8 +1,8 @@
+use std::collections::{BTreeMap, HashMap};
+
 use byteorder::{ByteOrder, NativeEndian};
-use failure::{Compat, ResultExt};
-use netlink_packet_core::{
-    NetlinkDeserializable, NetlinkHeader, NetlinkPayload, NetlinkSerializable,
-};
+use cecd_derive::GenlMessage;
+use failure::{format_err, Error};
 use netlink_packet_utils::{
     nla::{Nla, NlaBuffer, NlasIterator},
     parsers,
@@ -10,22 +10,22 @@ use netlink_packet_utils::{
     DecodeError,
 };
 
-use crate::genl::GenericBuffer;
+use netlink_sys::Socket;
+
+use crate::genl::{self, GenlFamily, Notifications, RawAttribute};
+
+pub const CTRL_FAMILY_NAME: &str = "nlctrl";
+
+/// Fixed id of the nlctrl family itself.
+pub const GENL_ID_CTRL: u16 = 0x10;
 
-pub const CTRL_CMD_UNSPEC: u8 = 0;
 pub const CTRL_CMD_NEWFAMILY: u8 = 1;
 pub const CTRL_CMD_DELFAMILY: u8 = 2;
 pub const CTRL_CMD_GETFAMILY: u8 = 3;
-pub const CTRL_CMD_NEWOPS: u8 = 4;
-pub const CTRL_CMD_DELOPS: u8 = 5;
-pub const CTRL_CMD_GETOPS: u8 = 6;
 pub const CTRL_CMD_NEWMCAST_GRP: u8 = 7;
 pub const CTRL_CMD_DELMCAST_GRP: u8 = 8;
-#[allow(unused)]
-pub const CTRL_CMD_GETMCAST_GRP: u8 = 9;
+pub const CTRL_CMD_GETPOLICY: u8 = 10;
 
-#[allow(unused)]
-pub const CTRL_ATTR_UNSPEC: u16 = 0;
 pub const CTRL_ATTR_FAMILY_ID: u16 = 1;
 pub const CTRL_ATTR_FAMILY_NAME: u16 = 2;
 pub const CTRL_ATTR_VERSION: u16 = 3;
@@ -33,21 +33,51 @@ pub const CTRL_ATTR_HDRSIZE: u16 = 4;
 pub const CTRL_ATTR_MAXATTR: u16 = 5;
 pub const CTRL_ATTR_OPS: u16 = 6;
 pub const CTRL_ATTR_MCAST_GROUPS: u16 = 7;
+pub const CTRL_ATTR_POLICY: u16 = 8;
+pub const CTRL_ATTR_OP_POLICY: u16 = 9;
 
-#[allow(unused)]
-pub const CTRL_ATTR_OP_UNSPEC: u16 = 0;
 pub const CTRL_ATTR_OP_ID: u16 = 1;
 pub const CTRL_ATTR_OP_FLAGS: u16 = 2;
 
-#[allow(unused)]
-pub const CTRL_ATTR_MCAST_GRP_UNSPEC: u16 = 0;
 pub const CTRL_ATTR_MCAST_GRP_NAME: u16 = 1;
 pub const CTRL_ATTR_MCAST_GRP_ID: u16 = 2;
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub const CTRL_ATTR_POLICY_DO: u16 = 1;
+pub const CTRL_ATTR_POLICY_DUMP: u16 = 2;
+
+pub const NL_POLICY_TYPE_ATTR_TYPE: u16 = 1;
+pub const NL_POLICY_TYPE_ATTR_MIN_VALUE_S: u16 = 2;
+pub const NL_POLICY_TYPE_ATTR_MAX_VALUE_S: u16 = 3;
+pub const NL_POLICY_TYPE_ATTR_MIN_VALUE_U: u16 = 4;
+pub const NL_POLICY_TYPE_ATTR_MAX_VALUE_U: u16 = 5;
+pub const NL_POLICY_TYPE_ATTR_MIN_LENGTH: u16 = 6;
+pub const NL_POLICY_TYPE_ATTR_MAX_LENGTH: u16 = 7;
+pub const NL_POLICY_TYPE_ATTR_POLICY_IDX: u16 = 8;
+pub const NL_POLICY_TYPE_ATTR_POLICY_MAXTYPE: u16 = 9;
+pub const NL_POLICY_TYPE_ATTR_BITFIELD32_MASK: u16 = 10;
+pub const NL_POLICY_TYPE_ATTR_MASK: u16 = 12;
+
+pub const NL_ATTR_TYPE_INVALID: u32 = 0;
+pub const NL_ATTR_TYPE_FLAG: u32 = 1;
+pub const NL_ATTR_TYPE_U8: u32 = 2;
+pub const NL_ATTR_TYPE_U16: u32 = 3;
+pub const NL_ATTR_TYPE_U32: u32 = 4;
+pub const NL_ATTR_TYPE_U64: u32 = 5;
+pub const NL_ATTR_TYPE_S8: u32 = 6;
+pub const NL_ATTR_TYPE_S16: u32 = 7;
+pub const NL_ATTR_TYPE_S32: u32 = 8;
+pub const NL_ATTR_TYPE_S64: u32 = 9;
+pub const NL_ATTR_TYPE_BINARY: u32 = 10;
+pub const NL_ATTR_TYPE_STRING: u32 = 11;
+pub const NL_ATTR_TYPE_NUL_STRING: u32 = 12;
+pub const NL_ATTR_TYPE_NESTED: u32 = 13;
+pub const NL_ATTR_TYPE_NESTED_ARRAY: u32 = 14;
+pub const NL_ATTR_TYPE_BITFIELD32: u32 = 15;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct FamilyId(u16);
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct FamilyName(String);
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -93,16 +123,26 @@ impl FamilyName {
     }
 }
 
+impl MulticastGroupName {
+    pub fn new<T: Into<String>>(s: T) -> Self {
+        Self(s.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Operation {
     id: OperationId,
     flags: OperationFlags,
 }
 
-impl_nested_attribute_parse! {
-    Operation:
+impl_nested_attribute! {
+    Operation, unknown => skip:
         CTRL_ATTR_OP_ID => id: OperationId,
-        CTRL_ATTR_OP_FLAGS => flags: OperationFlags,
+        CTRL_ATTR_OP_FLAGS => flags: OperationFlags = OperationFlags::default(),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -111,10 +151,21 @@ pub struct MulticastGroup {
     name: MulticastGroupName,
 }
 
-impl_nested_attribute_parse! {
-    MulticastGroup:
+// The kernel emits the group id before its name, do the same here.
+impl_nested_attribute! {
+    MulticastGroup, unknown => skip:
+        CTRL_ATTR_MCAST_GRP_ID => id: MulticastGroupId,
         CTRL_ATTR_MCAST_GRP_NAME => name: MulticastGroupName,
-        CTRL_ATTR_MCAST_GRP_ID => id: MulticastGroupId
+}
+
+impl MulticastGroup {
+    pub fn id(&self) -> MulticastGroupId {
+        self.id.clone()
+    }
+
+    pub fn name(&self) -> &MulticastGroupName {
+        &self.name
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -122,32 +173,32 @@ pub struct OperationList {
     operations: Vec<Operation>,
 }
 
-impl<'buffer, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'buffer T>> for OperationList {
-    fn parse(buffer: &NlaBuffer<&'buffer T>) -> Result<Self, DecodeError> {
-        let operations = NlasIterator::new(buffer.value())
-            .map(|attribute: Result<NlaBuffer<_>, _>| {
-                attribute.and_then(|attr| Operation::parse(&attr.value()))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(Self { operations })
-    }
-}
+impl_attribute_list!(OperationList { operations: Operation }: CTRL_ATTR_OPS);
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct MulticastGroupList {
     groups: Vec<MulticastGroup>,
 }
 
-impl<'buffer, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'buffer T>> for MulticastGroupList {
-    fn parse(buffer: &NlaBuffer<&'buffer T>) -> Result<Self, DecodeError> {
-        let groups = NlasIterator::new(buffer.value())
-            .map(|attribute: Result<NlaBuffer<_>, _>| {
-                attribute.and_then(|attr| MulticastGroup::parse(&attr.value()))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+impl_attribute_list!(MulticastGroupList { groups: MulticastGroup }: CTRL_ATTR_MCAST_GROUPS);
+
+impl MulticastGroupList {
+    pub fn iter(&self) -> impl Iterator<Item = &MulticastGroup> {
+        self.groups.iter()
+    }
 
-        Ok(Self { groups })
+    pub fn find(&self, name: &str) -> Option<&MulticastGroup> {
+        self.iter().find(|group| group.name().as_str() == name)
+    }
+
+    /// Add `group`, replacing any group with the same id.
+    pub fn insert(&mut self, group: MulticastGroup) {
+        self.remove(&group.id);
+        self.groups.push(group);
+    }
+
+    pub fn remove(&mut self, id: &MulticastGroupId) {
+        self.groups.retain(|group| &group.id != id);
     }
 }
 
@@ -162,104 +213,1130 @@ pub struct NewFamily {
     pub multicast_groups: MulticastGroupList,
 }
 
-impl_nested_attribute_parse! {
-    NewFamily:
+// Same order as the kernel's ctrl_fill_info(), which leaves out empty lists
+impl_nested_attribute! {
+    NewFamily, unknown => skip:
         CTRL_ATTR_FAMILY_NAME => name: FamilyName,
         CTRL_ATTR_FAMILY_ID => id: FamilyId,
         CTRL_ATTR_VERSION => version: Version,
         CTRL_ATTR_HDRSIZE => header_size: HeaderSize,
         CTRL_ATTR_MAXATTR => max_attributes: MaxAttributes,
-        CTRL_ATTR_OPS => operations: OperationList,
-        CTRL_ATTR_MCAST_GROUPS => multicast_groups: MulticastGroupList,
+        CTRL_ATTR_OPS => operations: OperationList = OperationList::default(),
+        CTRL_ATTR_MCAST_GROUPS => multicast_groups: MulticastGroupList = MulticastGroupList::default(),
+}
+
+/// Notification about multicast groups of a family being registered or unregistered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MulticastGroupEvent {
+    pub family_id: FamilyId,
+    pub family_name: FamilyName,
+    pub groups: MulticastGroupList,
 }
 
+// Same order as the kernel's ctrl_fill_mcgrp_info()
+impl_nested_attribute! {
+    MulticastGroupEvent, unknown => skip:
+        CTRL_ATTR_FAMILY_NAME => family_name: FamilyName,
+        CTRL_ATTR_FAMILY_ID => family_id: FamilyId,
+        CTRL_ATTR_MCAST_GROUPS => groups: MulticastGroupList,
+}
+
+kernel_enum! {
+    /// Type of an attribute as reported by the kernel's policy dump.
+    pub enum AttributeType: u32 {
+        Invalid = NL_ATTR_TYPE_INVALID,
+        Flag = NL_ATTR_TYPE_FLAG,
+        U8 = NL_ATTR_TYPE_U8,
+        U16 = NL_ATTR_TYPE_U16,
+        U32 = NL_ATTR_TYPE_U32,
+        U64 = NL_ATTR_TYPE_U64,
+        S8 = NL_ATTR_TYPE_S8,
+        S16 = NL_ATTR_TYPE_S16,
+        S32 = NL_ATTR_TYPE_S32,
+        S64 = NL_ATTR_TYPE_S64,
+        Binary = NL_ATTR_TYPE_BINARY,
+        String = NL_ATTR_TYPE_STRING,
+        NulString = NL_ATTR_TYPE_NUL_STRING,
+        Nested = NL_ATTR_TYPE_NESTED,
+        NestedArray = NL_ATTR_TYPE_NESTED_ARRAY,
+        Bitfield32 = NL_ATTR_TYPE_BITFIELD32,
+    }
+}
+
+/// Constraints the kernel enforces on a single attribute.
+///
+/// Which of the optional fields are present depends on the attribute type, e.g. only integers
+/// carry value ranges and only nested attributes refer to another policy table.
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributePolicy {
+    pub kind: AttributeType,
+    pub min_value_signed: Option<i64>,
+    pub max_value_signed: Option<i64>,
+    pub min_value_unsigned: Option<u64>,
+    pub max_value_unsigned: Option<u64>,
+    pub min_length: Option<u32>,
+    pub max_length: Option<u32>,
+    pub policy_index: Option<u32>,
+    pub policy_max_type: Option<u32>,
+    pub bitfield32_mask: Option<u32>,
+    pub mask: Option<u64>,
+}
+
+fn parse_i64(payload: &[u8]) -> Result<i64, DecodeError> {
+    if payload.len() != std::mem::size_of::<i64>() {
+        return Err(format!("invalid i64: {:?}", payload).into());
+    }
+    Ok(NativeEndian::read_i64(payload))
+}
+
+impl<T: AsRef<[u8]>> Parseable<T> for AttributePolicy {
+    fn parse(buffer: &T) -> Result<Self, DecodeError> {
+        let mut policy = AttributePolicy {
+            kind: AttributeType::Invalid,
+            min_value_signed: None,
+            max_value_signed: None,
+            min_value_unsigned: None,
+            max_value_unsigned: None,
+            min_length: None,
+            max_length: None,
+            policy_index: None,
+            policy_max_type: None,
+            bitfield32_mask: None,
+            mask: None,
+        };
+
+        for attribute in NlasIterator::new(buffer) {
+            let attribute = attribute?;
+            let value = attribute.value();
+            match attribute.kind() {
+                NL_POLICY_TYPE_ATTR_TYPE => policy.kind = parsers::parse_u32(value)?.into(),
+                NL_POLICY_TYPE_ATTR_MIN_VALUE_S => {
+                    policy.min_value_signed = Some(parse_i64(value)?)
+                }
+                NL_POLICY_TYPE_ATTR_MAX_VALUE_S => {
+                    policy.max_value_signed = Some(parse_i64(value)?)
+                }
+                NL_POLICY_TYPE_ATTR_MIN_VALUE_U => {
+                    policy.min_value_unsigned = Some(parsers::parse_u64(value)?)
+                }
+                NL_POLICY_TYPE_ATTR_MAX_VALUE_U => {
+                    policy.max_value_unsigned = Some(parsers::parse_u64(value)?)
+                }
+                NL_POLICY_TYPE_ATTR_MIN_LENGTH => {
+                    policy.min_length = Some(parsers::parse_u32(value)?)
+                }
+                NL_POLICY_TYPE_ATTR_MAX_LENGTH => {
+                    policy.max_length = Some(parsers::parse_u32(value)?)
+                }
+                NL_POLICY_TYPE_ATTR_POLICY_IDX => {
+                    policy.policy_index = Some(parsers::parse_u32(value)?)
+                }
+                NL_POLICY_TYPE_ATTR_POLICY_MAXTYPE => {
+                    policy.policy_max_type = Some(parsers::parse_u32(value)?)
+                }
+                NL_POLICY_TYPE_ATTR_BITFIELD32_MASK => {
+                    policy.bitfield32_mask = Some(parsers::parse_u32(value)?)
+                }
+                NL_POLICY_TYPE_ATTR_MASK => policy.mask = Some(parsers::parse_u64(value)?),
+                // Padding for 64-bit values and attributes added by newer kernels
+                _ => {}
+            }
+        }
+
+        Ok(policy)
+    }
+}
+
+impl AttributePolicy {
+    fn to_attributes(&self) -> Vec<RawAttribute> {
+        let mut attributes = vec![RawAttribute::u32(
+            NL_POLICY_TYPE_ATTR_TYPE,
+            self.kind.into(),
+        )];
+        let optional = [
+            (
+                NL_POLICY_TYPE_ATTR_MIN_VALUE_S,
+                self.min_value_signed.map(|v| v.to_ne_bytes()),
+            ),
+            (
+                NL_POLICY_TYPE_ATTR_MAX_VALUE_S,
+                self.max_value_signed.map(|v| v.to_ne_bytes()),
+            ),
+            (
+                NL_POLICY_TYPE_ATTR_MIN_VALUE_U,
+                self.min_value_unsigned.map(|v| v.to_ne_bytes()),
+            ),
+            (
+                NL_POLICY_TYPE_ATTR_MAX_VALUE_U,
+                self.max_value_unsigned.map(|v| v.to_ne_bytes()),
+            ),
+            (NL_POLICY_TYPE_ATTR_MASK, self.mask.map(|v| v.to_ne_bytes())),
+        ];
+        attributes.extend(optional.iter().filter_map(|(kind, value)| {
+            value.map(|value| RawAttribute::new(*kind, value.to_vec()))
+        }));
+
+        let optional = [
+            (NL_POLICY_TYPE_ATTR_MIN_LENGTH, self.min_length),
+            (NL_POLICY_TYPE_ATTR_MAX_LENGTH, self.max_length),
+            (NL_POLICY_TYPE_ATTR_POLICY_IDX, self.policy_index),
+            (NL_POLICY_TYPE_ATTR_POLICY_MAXTYPE, self.policy_max_type),
+            (NL_POLICY_TYPE_ATTR_BITFIELD32_MASK, self.bitfield32_mask),
+        ];
+        attributes.extend(
+            optional
+                .iter()
+                .filter_map(|(kind, value)| value.map(|value| RawAttribute::u32(*kind, value))),
+        );
+
+        attributes
+    }
+}
+
+/// The policy of attribute `attribute` in the policy table with index `table`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyAttribute {
+    pub table: u32,
+    pub attribute: u16,
+    pub policy: AttributePolicy,
+}
+
+/// Policy tables used to validate the attributes of a command.
+///
+/// A missing table means that the command does not accept any attributes in that mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationPolicy {
+    pub command: u32,
+    pub do_policy: Option<u32>,
+    pub dump_policy: Option<u32>,
+}
+
+impl<T: AsRef<[u8]>> ParseableParametrized<T, u32> for OperationPolicy {
+    fn parse_with_param(buffer: &T, command: u32) -> Result<Self, DecodeError> {
+        let mut policy = OperationPolicy {
+            command,
+            do_policy: None,
+            dump_policy: None,
+        };
+
+        for attribute in NlasIterator::new(buffer) {
+            let attribute = attribute?;
+            match attribute.kind() {
+                CTRL_ATTR_POLICY_DO => {
+                    policy.do_policy = Some(parsers::parse_u32(attribute.value())?)
+                }
+                CTRL_ATTR_POLICY_DUMP => {
+                    policy.dump_policy = Some(parsers::parse_u32(attribute.value())?)
+                }
+                _ => {}
+            }
+        }
+
+        Ok(policy)
+    }
+}
+
+/// A single reply to `CTRL_CMD_GETPOLICY`.
+///
+/// The kernel splits a policy dump into many messages, each describing a handful of attributes
+/// or operations. Use [`Policy`] to merge them.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PolicyMessage {
+    pub family_id: Option<FamilyId>,
+    pub attributes: Vec<PolicyAttribute>,
+    pub operations: Vec<OperationPolicy>,
+}
+
+impl<T: AsRef<[u8]>> Parseable<T> for PolicyMessage {
+    fn parse(buffer: &T) -> Result<Self, DecodeError> {
+        let mut message = PolicyMessage::default();
+
+        for attribute in NlasIterator::new(buffer) {
+            let attribute = attribute?;
+            match attribute.kind() {
+                CTRL_ATTR_FAMILY_ID => message.family_id = Some(FamilyId::parse(&attribute)?),
+                CTRL_ATTR_POLICY => {
+                    for table in NlasIterator::new(attribute.value()) {
+                        let table = table?;
+                        for entry in NlasIterator::new(table.value()) {
+                            let entry = entry?;
+                            message.attributes.push(PolicyAttribute {
+                                table: table.kind().into(),
+                                attribute: entry.kind(),
+                                policy: AttributePolicy::parse(&entry.value())?,
+                            });
+                        }
+                    }
+                }
+                CTRL_ATTR_OP_POLICY => {
+                    for operation in NlasIterator::new(attribute.value()) {
+                        let operation = operation?;
+                        message.operations.push(OperationPolicy::parse_with_param(
+                            &operation.value(),
+                            operation.kind().into(),
+                        )?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(message)
+    }
+}
+
+impl Emitable for PolicyMessage {
+    fn buffer_len(&self) -> usize {
+        self.to_attributes().as_slice().buffer_len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) {
+        self.to_attributes().as_slice().emit(buffer)
+    }
+}
+
+impl PolicyMessage {
+    fn to_attributes(&self) -> Vec<RawAttribute> {
+        let mut attributes = Vec::new();
+
+        if let Some(id) = &self.family_id {
+            attributes.push(RawAttribute::u16(CTRL_ATTR_FAMILY_ID, id.0));
+        }
+
+        if !self.attributes.is_empty() {
+            let tables = self
+                .attributes
+                .iter()
+                .map(|attribute| {
+                    let entry = RawAttribute::nested(
+                        attribute.attribute,
+                        &attribute.policy.to_attributes(),
+                    );
+                    RawAttribute::nested(attribute.table as u16, &[entry])
+                })
+                .collect::<Vec<_>>();
+            attributes.push(RawAttribute::nested(CTRL_ATTR_POLICY, &tables));
+        }
+
+        if !self.operations.is_empty() {
+            let operations = self
+                .operations
+                .iter()
+                .map(|operation| {
+                    let tables = [
+                        (CTRL_ATTR_POLICY_DO, operation.do_policy),
+                        (CTRL_ATTR_POLICY_DUMP, operation.dump_policy),
+                    ]
+                    .iter()
+                    .filter_map(|(kind, index)| index.map(|index| RawAttribute::u32(*kind, index)))
+                    .collect::<Vec<_>>();
+                    RawAttribute::nested(operation.command as u16, &tables)
+                })
+                .collect::<Vec<_>>();
+            attributes.push(RawAttribute::nested(CTRL_ATTR_OP_POLICY, &operations));
+        }
+
+        attributes
+    }
+}
+
+/// Attribute policies of a whole family, merged from a `CTRL_CMD_GETPOLICY` dump.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Policy {
+    pub tables: BTreeMap<u32, BTreeMap<u16, AttributePolicy>>,
+    pub operations: BTreeMap<u32, OperationPolicy>,
+}
+
+impl Policy {
+    pub fn from_messages<I: IntoIterator<Item = PolicyMessage>>(messages: I) -> Self {
+        let mut policy = Policy::default();
+
+        for message in messages {
+            for attribute in message.attributes {
+                policy
+                    .tables
+                    .entry(attribute.table)
+                    .or_default()
+                    .insert(attribute.attribute, attribute.policy);
+            }
+
+            for operation in message.operations {
+                policy.operations.insert(operation.command, operation);
+            }
+        }
+
+        policy
+    }
+
+    /// Attributes accepted by `command` when sent as a regular (non-dump) request.
+    ///
+    /// Kernels before 5.10 do not report per-operation policies, in which case the family's
+    /// single policy table applies to all commands.
+    pub fn do_attributes(&self, command: u8) -> Option<&BTreeMap<u16, AttributePolicy>> {
+        if self.operations.is_empty() {
+            return self.tables.get(&0);
+        }
+
+        self.operations
+            .get(&command.into())
+            .and_then(|operation| operation.do_policy)
+            .and_then(|index| self.tables.get(&index))
+    }
+
+    /// Attributes accepted by `command` when sent with `NLM_F_DUMP`.
+    ///
+    /// Like [`do_attributes`](Self::do_attributes), falls back to the family's single policy
+    /// table on kernels before 5.10.
+    pub fn dump_attributes(&self, command: u8) -> Option<&BTreeMap<u16, AttributePolicy>> {
+        if self.operations.is_empty() {
+            return self.tables.get(&0);
+        }
+
+        self.operations
+            .get(&command.into())
+            .and_then(|operation| operation.dump_policy)
+            .and_then(|index| self.tables.get(&index))
+    }
+
+    /// Check that `command` accepts each of `attributes` as a regular request.
+    pub fn check(&self, command: u8, attributes: &[u16]) -> Result<(), Error> {
+        self.check_attributes(command, self.do_attributes(command), attributes)
+    }
+
+    /// Check that `command` accepts each of `attributes` as a dump request.
+    pub fn check_dump(&self, command: u8, attributes: &[u16]) -> Result<(), Error> {
+        self.check_attributes(command, self.dump_attributes(command), attributes)
+    }
+
+    fn check_attributes(
+        &self,
+        command: u8,
+        accepted: Option<&BTreeMap<u16, AttributePolicy>>,
+        attributes: &[u16],
+    ) -> Result<(), Error> {
+        if !self.operations.is_empty() && !self.operations.contains_key(&command.into()) {
+            return Err(format_err!("command {} is not supported", command));
+        }
+
+        for attribute in attributes {
+            if !accepted.is_some_and(|table| table.contains_key(attribute)) {
+                return Err(format_err!(
+                    "attribute {} is not accepted by command {}",
+                    attribute,
+                    command
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, GenlMessage)]
+#[genl(version = 1)]
 pub enum ControlMessage {
+    #[genl(cmd = CTRL_CMD_NEWFAMILY, version = 2, parse)]
     NewFamily(NewFamily),
+    /// Notification that a family was unregistered, carrying its last known description.
+    #[genl(cmd = CTRL_CMD_DELFAMILY, version = 2, parse)]
+    DelFamily(NewFamily),
+    #[genl(cmd = CTRL_CMD_NEWMCAST_GRP, version = 2, parse)]
+    NewMulticastGroup(MulticastGroupEvent),
+    #[genl(cmd = CTRL_CMD_DELMCAST_GRP, version = 2, parse)]
+    DelMulticastGroup(MulticastGroupEvent),
+    #[genl(cmd = CTRL_CMD_GETFAMILY)]
     GetFamily(FamilyName),
+    /// Request every registered family, to be sent with `NLM_F_DUMP`.
+    #[genl(cmd = CTRL_CMD_GETFAMILY)]
+    DumpFamilies,
+    /// Request the attribute policies of a family, to be sent with `NLM_F_DUMP`.
+    #[genl(cmd = CTRL_CMD_GETPOLICY)]
+    GetPolicy(FamilyName),
+    #[genl(cmd = CTRL_CMD_GETPOLICY, parse)]
+    Policy(PolicyMessage),
+}
+
+/// The nlctrl family, which has a fixed id and is used to look up all other families.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlFamily;
+
+impl GenlFamily for ControlFamily {
+    type Message = ControlMessage;
+
+    fn name(&self) -> FamilyName {
+        FamilyName::new(CTRL_FAMILY_NAME)
+    }
+
+    fn id(&self) -> FamilyId {
+        FamilyId(GENL_ID_CTRL)
+    }
+}
+
+impl ControlFamily {
+    /// Listen for family and multicast group (un)registrations on the "notify" group, looking
+    /// the group up on `socket`.
+    pub fn notifications(&self, socket: &Socket) -> Result<Notifications<Self>, Error> {
+        match genl::request(
+            socket,
+            self.tag_message(ControlMessage::GetFamily(self.name())),
+        )? {
+            ControlMessage::NewFamily(nlctrl) => Notifications::new(&nlctrl, &[CTRL_NOTIFY_GROUP]),
+            reply => Err(format_err!("unexpected reply to GETFAMILY: {:?}", reply)),
+        }
+    }
 }
 
 impl ControlMessage {
-    pub fn attribute_size(&self) -> usize {
-        match self {
-            ControlMessage::NewFamily(family) => family.id.buffer_len() + family.name.buffer_len(),
-            ControlMessage::GetFamily(name) => name.buffer_len(),
+    /// List all generic netlink families known to the running kernel.
+    pub fn dump_families(socket: &Socket) -> Result<Vec<NewFamily>, Error> {
+        let families = genl::dump(
+            socket,
+            ControlFamily.tag_message(ControlMessage::DumpFamilies),
+        )?
+        .into_iter()
+        .filter_map(|reply| match reply {
+            ControlMessage::NewFamily(family) => Some(family),
+            _ => None,
+        })
+        .collect();
+
+        Ok(families)
+    }
+
+    /// Fetch the attribute policies the kernel enforces for `family`.
+    ///
+    /// Requires Linux 5.7 or newer; older kernels reject the request.
+    pub fn get_policy(socket: &Socket, family: FamilyName) -> Result<Policy, Error> {
+        let messages = genl::dump(
+            socket,
+            ControlFamily.tag_message(ControlMessage::GetPolicy(family)),
+        )?
+        .into_iter()
+        .filter_map(|reply| match reply {
+            ControlMessage::Policy(policy) => Some(policy),
+            _ => None,
+        });
+
+        Ok(Policy::from_messages(messages))
+    }
+}
+
+/// Cache of resolved generic netlink families.
+///
+/// Families are looked up by name or id, resolving unknown names with `CTRL_CMD_GETFAMILY`. Feed
+/// it the events of [`ControlFamily::notifications`] to drop families when they are unregistered.
+#[derive(Debug, Default)]
+pub struct FamilyRegistry {
+    families: HashMap<FamilyName, NewFamily>,
+    names: HashMap<FamilyId, FamilyName>,
+}
+
+impl FamilyRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `family`, replacing a family with the same name or id.
+    ///
+    /// A family with the same id is stale, the kernel reused its id after it went away without
+    /// a notification reaching us.
+    pub fn insert(&mut self, family: NewFamily) {
+        if let Some(stale) = self.families.remove(&family.name) {
+            self.names.remove(&stale.id);
         }
+        if let Some(stale) = self.by_id(&family.id).map(|stale| stale.name.clone()) {
+            self.families.remove(&stale);
+        }
+
+        self.names.insert(family.id.clone(), family.name.clone());
+        self.families.insert(family.name.clone(), family);
+    }
+
+    pub fn remove(&mut self, name: &FamilyName) -> Option<NewFamily> {
+        let family = self.families.remove(name)?;
+        self.names.remove(&family.id);
+        Some(family)
+    }
+
+    pub fn by_name(&self, name: &FamilyName) -> Option<&NewFamily> {
+        self.families.get(name)
+    }
+
+    pub fn by_id(&self, id: &FamilyId) -> Option<&NewFamily> {
+        self.names.get(id).and_then(|name| self.by_name(name))
     }
 
-    fn command(&self) -> u8 {
-        match self {
-            ControlMessage::NewFamily(_) => CTRL_CMD_NEWFAMILY,
-            ControlMessage::GetFamily(_) => CTRL_CMD_GETFAMILY,
+    /// Look up `name` in the cache, asking the kernel if it is not known yet.
+    pub fn resolve(&mut self, socket: &Socket, name: &FamilyName) -> Result<&NewFamily, Error> {
+        if !self.families.contains_key(name) {
+            match genl::request(
+                socket,
+                ControlFamily.tag_message(ControlMessage::GetFamily(name.clone())),
+            )? {
+                ControlMessage::NewFamily(family) => self.insert(family),
+                reply => return Err(format_err!("unexpected reply to GETFAMILY: {:?}", reply)),
+            }
         }
+
+        self.by_name(name).ok_or_else(|| {
+            format_err!(
+                "kernel replied with a different family than {}",
+                name.as_str()
+            )
+        })
     }
 
-    fn version(&self) -> u8 {
-        match self {
-            ControlMessage::NewFamily(_) => 2,
-            _ => 1,
+    /// Replace the cache with `families` from a fresh dump, after notifications were lost.
+    ///
+    /// Returns the `DelFamily` and `NewFamily` events that were missed: families that went
+    /// away, and families that are new or came back under another id.
+    pub fn replace_all(&mut self, families: Vec<NewFamily>) -> Vec<ControlMessage> {
+        let mut missed = Vec::new();
+
+        for known in self.families.values() {
+            let current = families.iter().find(|family| family.name == known.name);
+            if current.is_none_or(|family| family.id != known.id) {
+                missed.push(ControlMessage::DelFamily(known.clone()));
+            }
+        }
+        for family in &families {
+            let known = self.by_name(&family.name);
+            if known.is_none_or(|known| known.id != family.id) {
+                missed.push(ControlMessage::NewFamily(family.clone()));
+            }
+        }
+
+        *self = Self::new();
+        for family in families {
+            self.insert(family);
         }
+
+        missed
     }
 
-    fn emit_attributes(&self, buffer: &mut [u8]) {
-        match self {
-            ControlMessage::NewFamily(_family) => unimplemented!(),
-            ControlMessage::GetFamily(name) => name.emit(buffer),
+    /// Update the cache according to a notification from the nlctrl "notify" group.
+    pub fn handle_event(&mut self, event: &ControlMessage) {
+        match event {
+            ControlMessage::NewFamily(family) => self.insert(family.clone()),
+            ControlMessage::DelFamily(family) => {
+                self.remove(&family.name);
+            }
+            ControlMessage::NewMulticastGroup(event) => {
+                if let Some(family) = self.families.get_mut(&event.family_name) {
+                    for group in event.groups.iter() {
+                        family.multicast_groups.insert(group.clone());
+                    }
+                }
+            }
+            ControlMessage::DelMulticastGroup(event) => {
+                if let Some(family) = self.families.get_mut(&event.family_name) {
+                    for group in event.groups.iter() {
+                        family.multicast_groups.remove(&group.id);
+                    }
+                }
+            }
+            _ => {}
         }
     }
 }
 
-impl NetlinkSerializable<ControlMessage> for ControlMessage {
-    fn buffer_len(&self) -> usize {
-        4 + self.attribute_size()
+/// Name of the nlctrl multicast group that family and group (un)registrations are sent to.
+pub const CTRL_NOTIFY_GROUP: &str = "notify";
+
+#[cfg(test)]
+mod tests {
+    use netlink_packet_core::NetlinkSerializable;
+    use netlink_packet_utils::nla::NLA_F_NESTED;
+
+    use super::*;
+    use crate::genl::{GenericBuffer, GenlPayload, GENL_HDRLEN};
+
+    /// Reply of Linux 6.18 to `CTRL_CMD_GETFAMILY` for "nlctrl", without the netlink header.
+    ///
+    /// The kernel starts these nests without `NLA_F_NESTED`.
+    #[rustfmt::skip]
+    const NLCTRL_FAMILY: &[u8] = &[
+        // CTRL_CMD_NEWFAMILY, version 2
+        0x01, 0x02, 0x00, 0x00,
+        // CTRL_ATTR_FAMILY_NAME "nlctrl"
+        0x0b, 0x00, 0x02, 0x00, 0x6e, 0x6c, 0x63, 0x74, 0x72, 0x6c, 0x00, 0x00,
+        // CTRL_ATTR_FAMILY_ID 0x10
+        0x06, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_VERSION 2
+        0x08, 0x00, 0x03, 0x00, 0x02, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_HDRSIZE 0
+        0x08, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_MAXATTR 0
+        0x08, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_OPS
+        0x2c, 0x00, 0x06, 0x00,
+            // 1: CTRL_CMD_GETFAMILY with flags 0x0e
+            0x14, 0x00, 0x01, 0x00,
+                0x08, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00,
+                0x08, 0x00, 0x02, 0x00, 0x0e, 0x00, 0x00, 0x00,
+            // 2: CTRL_CMD_GETPOLICY with flags 0x0c
+            0x14, 0x00, 0x02, 0x00,
+                0x08, 0x00, 0x01, 0x00, 0x0a, 0x00, 0x00, 0x00,
+                0x08, 0x00, 0x02, 0x00, 0x0c, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_MCAST_GROUPS
+        0x1c, 0x00, 0x07, 0x00,
+            // 1: "notify" with id 0x10
+            0x18, 0x00, 0x01, 0x00,
+                0x08, 0x00, 0x02, 0x00, 0x10, 0x00, 0x00, 0x00,
+                0x0b, 0x00, 0x01, 0x00, 0x6e, 0x6f, 0x74, 0x69, 0x66, 0x79, 0x00, 0x00,
+    ];
+
+    /// `CTRL_CMD_DELFAMILY` notification of nl80211 being unregistered, in the layout of
+    /// ctrl_fill_info() and with the operations cut down to one.
+    #[rustfmt::skip]
+    const NL80211_DELFAMILY: &[u8] = &[
+        // CTRL_CMD_DELFAMILY, version 2
+        0x02, 0x02, 0x00, 0x00,
+        // CTRL_ATTR_FAMILY_NAME "nl80211"
+        0x0c, 0x00, 0x02, 0x00, 0x6e, 0x6c, 0x38, 0x30, 0x32, 0x31, 0x31, 0x00,
+        // CTRL_ATTR_FAMILY_ID 0x1a
+        0x06, 0x00, 0x01, 0x00, 0x1a, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_VERSION 1
+        0x08, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_HDRSIZE 0
+        0x08, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_MAXATTR 320
+        0x08, 0x00, 0x05, 0x00, 0x40, 0x01, 0x00, 0x00,
+        // CTRL_ATTR_OPS
+        0x18, 0x00, 0x06, 0x00,
+            // 1: NL80211_CMD_GET_WIPHY with flags 0x0e
+            0x14, 0x00, 0x01, 0x00,
+                0x08, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00,
+                0x08, 0x00, 0x02, 0x00, 0x0e, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_MCAST_GROUPS
+        0x34, 0x00, 0x07, 0x00,
+            // 1: "config" with id 4
+            0x18, 0x00, 0x01, 0x00,
+                0x08, 0x00, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00,
+                0x0b, 0x00, 0x01, 0x00, 0x63, 0x6f, 0x6e, 0x66, 0x69, 0x67, 0x00, 0x00,
+            // 2: "mlme" with id 7
+            0x18, 0x00, 0x02, 0x00,
+                0x08, 0x00, 0x02, 0x00, 0x07, 0x00, 0x00, 0x00,
+                0x09, 0x00, 0x01, 0x00, 0x6d, 0x6c, 0x6d, 0x65, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// `CTRL_CMD_NEWMCAST_GRP` notification of nl80211 registering another group, in the layout
+    /// of ctrl_fill_mcgrp_info().
+    #[rustfmt::skip]
+    const NL80211_NEWMCAST_GRP: &[u8] = &[
+        // CTRL_CMD_NEWMCAST_GRP, version 2
+        0x07, 0x02, 0x00, 0x00,
+        // CTRL_ATTR_FAMILY_NAME "nl80211"
+        0x0c, 0x00, 0x02, 0x00, 0x6e, 0x6c, 0x38, 0x30, 0x32, 0x31, 0x31, 0x00,
+        // CTRL_ATTR_FAMILY_ID 0x1a
+        0x06, 0x00, 0x01, 0x00, 0x1a, 0x00, 0x00, 0x00,
+        // CTRL_ATTR_MCAST_GROUPS
+        0x1c, 0x00, 0x07, 0x00,
+            // 1: "vendor" with id 9
+            0x18, 0x00, 0x01, 0x00,
+                0x08, 0x00, 0x02, 0x00, 0x09, 0x00, 0x00, 0x00,
+                0x0b, 0x00, 0x01, 0x00, 0x76, 0x65, 0x6e, 0x64, 0x6f, 0x72, 0x00, 0x00,
+    ];
+
+    /// Dump of Linux 6.18 in reply to `CTRL_CMD_GETPOLICY` for "nlctrl", one message per entry
+    /// and without the netlink headers.
+    #[rustfmt::skip]
+    const NLCTRL_POLICY: &[&[u8]] = &[
+        // CTRL_CMD_GETFAMILY validates with table 0, for requests and dumps
+        &[
+            0x0a, 0x02, 0x00, 0x00,
+            0x06, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00,
+            0x18, 0x00, 0x09, 0x80,
+                0x14, 0x00, 0x03, 0x80,
+                    0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    0x08, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ],
+        // CTRL_CMD_GETPOLICY only dumps, with table 1. The kernel takes the command from its
+        // missing do handler, hence 0.
+        &[
+            0x0a, 0x02, 0x00, 0x00,
+            0x06, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x09, 0x80,
+                0x0c, 0x00, 0x00, 0x80,
+                    0x08, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00,
+        ],
+        // Table 0, CTRL_ATTR_FAMILY_ID: u16 from 0 to 0xffff
+        &[
+            0x0a, 0x02, 0x00, 0x00,
+            0x06, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00,
+            0x2c, 0x00, 0x08, 0x80,
+                0x28, 0x00, 0x00, 0x80,
+                    0x24, 0x00, 0x01, 0x80,
+                        0x0c, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                        0x0c, 0x00, 0x05, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                        0x08, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00,
+        ],
+        // Table 0, CTRL_ATTR_FAMILY_NAME: NUL-terminated string of at most 15 bytes
+        &[
+            0x0a, 0x02, 0x00, 0x00,
+            0x06, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00,
+            0x1c, 0x00, 0x08, 0x80,
+                0x18, 0x00, 0x00, 0x80,
+                    0x14, 0x00, 0x02, 0x80,
+                        0x08, 0x00, 0x07, 0x00, 0x0f, 0x00, 0x00, 0x00,
+                        0x08, 0x00, 0x01, 0x00, 0x0c, 0x00, 0x00, 0x00,
+        ],
+        // Table 1, CTRL_ATTR_FAMILY_ID
+        &[
+            0x0a, 0x02, 0x00, 0x00,
+            0x06, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00,
+            0x2c, 0x00, 0x08, 0x80,
+                0x28, 0x00, 0x01, 0x80,
+                    0x24, 0x00, 0x01, 0x80,
+                        0x0c, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                        0x0c, 0x00, 0x05, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                        0x08, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00,
+        ],
+        // Table 1, CTRL_ATTR_FAMILY_NAME
+        &[
+            0x0a, 0x02, 0x00, 0x00,
+            0x06, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00,
+            0x1c, 0x00, 0x08, 0x80,
+                0x18, 0x00, 0x01, 0x80,
+                    0x14, 0x00, 0x02, 0x80,
+                        0x08, 0x00, 0x07, 0x00, 0x0f, 0x00, 0x00, 0x00,
+                        0x08, 0x00, 0x01, 0x00, 0x0c, 0x00, 0x00, 0x00,
+        ],
+        // Table 1, CTRL_ATTR_OP: u32 from 0 to 0xffffffff
+        &[
+            0x0a, 0x02, 0x00, 0x00,
+            0x06, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00,
+            0x2c, 0x00, 0x08, 0x80,
+                0x28, 0x00, 0x01, 0x80,
+                    0x24, 0x00, 0x0a, 0x80,
+                        0x0c, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                        0x0c, 0x00, 0x05, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
+                        0x08, 0x00, 0x01, 0x00, 0x04, 0x00, 0x00, 0x00,
+        ],
+    ];
+
+    fn parse(payload: &[u8]) -> ControlMessage {
+        let buffer = GenericBuffer::new_checked(payload).unwrap();
+        <ControlMessage as GenlPayload>::parse(&buffer).unwrap()
+    }
+
+    /// Clear `NLA_F_NESTED` on `attributes`, recursing into the attributes it was set on.
+    fn clear_nested_flags(attributes: &mut [u8]) {
+        let mut offset = 0;
+        while offset + 4 <= attributes.len() {
+            let length = NativeEndian::read_u16(&attributes[offset..]) as usize;
+            let kind = NativeEndian::read_u16(&attributes[offset + 2..]);
+            if kind & NLA_F_NESTED != 0 {
+                NativeEndian::write_u16(&mut attributes[offset + 2..], kind & !NLA_F_NESTED);
+                clear_nested_flags(&mut attributes[offset + 4..offset + length]);
+            }
+            offset += (length + 3) & !3;
+        }
     }
 
-    fn message_type(&self) -> u16 {
-        0x10
+    fn emit(message: ControlMessage) -> Vec<u8> {
+        let message = ControlFamily.tag_message(message);
+        let mut buffer = vec![0; NetlinkSerializable::buffer_len(&message)];
+        message.serialize(&mut buffer);
+        buffer
     }
 
-    fn serialize(&self, buffer: &mut [u8]) {
-        buffer[0] = self.command();
-        buffer[1] = self.version();
-        buffer[2] = 0;
-        buffer[3] = 0;
+    #[test]
+    fn new_family_round_trip() {
+        let message = parse(NLCTRL_FAMILY);
+        let family = match &message {
+            ControlMessage::NewFamily(family) => family,
+            other => panic!("expected NewFamily, got {:?}", other),
+        };
+
+        assert_eq!(family.name, FamilyName::new(CTRL_FAMILY_NAME));
+        assert_eq!(family.id, FamilyId(GENL_ID_CTRL));
+        assert_eq!(family.version, Version(2));
+        assert_eq!(
+            family.operations.operations,
+            vec![
+                Operation {
+                    id: OperationId(CTRL_CMD_GETFAMILY.into()),
+                    flags: OperationFlags(0x0e),
+                },
+                Operation {
+                    id: OperationId(CTRL_CMD_GETPOLICY.into()),
+                    flags: OperationFlags(0x0c),
+                },
+            ]
+        );
+        assert_eq!(
+            family.multicast_groups.groups,
+            vec![MulticastGroup {
+                id: MulticastGroupId(0x10),
+                name: MulticastGroupName::new(CTRL_NOTIFY_GROUP),
+            }]
+        );
 
-        self.emit_attributes(&mut buffer[4..]);
+        let emitted = emit(message.clone());
+        assert_eq!(parse(&emitted), message);
+
+        for attribute in NlasIterator::new(&emitted[GENL_HDRLEN..]) {
+            let attribute = attribute.unwrap();
+            let nested = [CTRL_ATTR_OPS, CTRL_ATTR_MCAST_GROUPS].contains(&attribute.kind());
+            assert_eq!(attribute.nested_flag(), nested);
+        }
+
+        let mut unflagged = emitted;
+        clear_nested_flags(&mut unflagged[GENL_HDRLEN..]);
+        assert_eq!(unflagged, NLCTRL_FAMILY);
     }
-}
 
-impl From<ControlMessage> for NetlinkPayload<ControlMessage> {
-    fn from(message: ControlMessage) -> Self {
-        NetlinkPayload::InnerMessage(message)
+    #[test]
+    fn policy_dump() {
+        let policy =
+            Policy::from_messages(NLCTRL_POLICY.iter().map(|payload| match parse(payload) {
+                ControlMessage::Policy(message) => message,
+                other => panic!("expected Policy, got {:?}", other),
+            }));
+
+        let table = &policy.tables[&0];
+        assert_eq!(table.len(), 2);
+        let family_id = &table[&CTRL_ATTR_FAMILY_ID];
+        assert_eq!(family_id.kind, AttributeType::U16);
+        assert_eq!(family_id.min_value_unsigned, Some(0));
+        assert_eq!(family_id.max_value_unsigned, Some(0xffff));
+        let family_name = &table[&CTRL_ATTR_FAMILY_NAME];
+        assert_eq!(family_name.kind, AttributeType::NulString);
+        assert_eq!(family_name.max_length, Some(15));
+        assert_eq!(policy.tables[&1][&10].kind, AttributeType::U32);
+
+        assert_eq!(
+            policy.operations[&CTRL_CMD_GETFAMILY.into()],
+            OperationPolicy {
+                command: CTRL_CMD_GETFAMILY.into(),
+                do_policy: Some(0),
+                dump_policy: Some(0),
+            }
+        );
+
+        policy
+            .check(
+                CTRL_CMD_GETFAMILY,
+                &[CTRL_ATTR_FAMILY_ID, CTRL_ATTR_FAMILY_NAME],
+            )
+            .unwrap();
+        // Only accepted by CTRL_CMD_GETPOLICY
+        assert!(policy.check(CTRL_CMD_GETFAMILY, &[10]).is_err());
+        assert!(policy.check(CTRL_CMD_NEWMCAST_GRP, &[]).is_err());
+
+        // The entry of CTRL_CMD_GETPOLICY, reported as command 0, only has a dump policy
+        policy.check_dump(0, &[CTRL_ATTR_FAMILY_NAME, 10]).unwrap();
+        assert!(policy.check(0, &[CTRL_ATTR_FAMILY_NAME]).is_err());
+        policy
+            .check_dump(CTRL_CMD_GETFAMILY, &[CTRL_ATTR_FAMILY_NAME])
+            .unwrap();
     }
-}
 
-impl<'buffer, B: AsRef<[u8]> + ?Sized> ParseableParametrized<GenericBuffer<&'buffer B>, u16>
-    for ControlMessage
-{
-    fn parse_with_param(
-        buffer: &GenericBuffer<&'buffer B>,
-        message_type: u16,
-    ) -> Result<Self, DecodeError> {
-        match message_type {
-            0x10 => match buffer.command() {
-                CTRL_CMD_NEWFAMILY => {
-                    let new_family = NewFamily::parse(&buffer.attributes())?;
+    #[test]
+    fn attribute_policy_round_trip() {
+        let policy = match parse(NLCTRL_POLICY[6]) {
+            ControlMessage::Policy(message) => message,
+            other => panic!("expected Policy, got {:?}", other),
+        };
+        let attribute = &policy.attributes[0];
+        assert_eq!(u32::from(attribute.policy.kind), NL_ATTR_TYPE_U32);
 
-                    Ok(ControlMessage::NewFamily(new_family))
-                }
-                cmd => Err(format!("unsupported command {}", cmd).into()),
+        let emitted = emit(ControlMessage::Policy(policy.clone()));
+        assert_eq!(parse(&emitted), ControlMessage::Policy(policy));
+    }
+
+    #[test]
+    fn family_deleted() {
+        let family = match parse(NL80211_DELFAMILY) {
+            ControlMessage::DelFamily(family) => family,
+            other => panic!("expected DelFamily, got {:?}", other),
+        };
+
+        assert_eq!(family.name, FamilyName::new("nl80211"));
+        assert_eq!(family.id, FamilyId(0x1a));
+        assert_eq!(family.version, Version(1));
+        assert_eq!(family.max_attributes, MaxAttributes(320));
+        assert_eq!(
+            family.operations.operations,
+            vec![Operation {
+                id: OperationId(1),
+                flags: OperationFlags(0x0e),
+            }]
+        );
+        assert_eq!(
+            family.multicast_groups.find("mlme").map(MulticastGroup::id),
+            Some(MulticastGroupId(7))
+        );
+        assert_eq!(family.multicast_groups.iter().count(), 2);
+    }
+
+    #[test]
+    fn multicast_group_added() {
+        let event = match parse(NL80211_NEWMCAST_GRP) {
+            ControlMessage::NewMulticastGroup(event) => event,
+            other => panic!("expected NewMulticastGroup, got {:?}", other),
+        };
+
+        assert_eq!(event.family_name, FamilyName::new("nl80211"));
+        assert_eq!(event.family_id, FamilyId(0x1a));
+        assert_eq!(
+            event.groups.groups,
+            vec![MulticastGroup {
+                id: MulticastGroupId(9),
+                name: MulticastGroupName::new("vendor"),
+            }]
+        );
+    }
+
+    fn family(name: &str, id: u16, groups: &[(&str, u32)]) -> NewFamily {
+        NewFamily {
+            id: FamilyId(id),
+            name: FamilyName::new(name),
+            version: Version(1),
+            header_size: HeaderSize(0),
+            max_attributes: MaxAttributes(0),
+            operations: OperationList::default(),
+            multicast_groups: MulticastGroupList {
+                groups: groups
+                    .iter()
+                    .map(|(name, id)| MulticastGroup {
+                        id: MulticastGroupId(*id),
+                        name: MulticastGroupName::new(*name),
+                    })
+                    .collect(),
             },
-            t => Err(format!("unknown message type {}", t).into()),
         }
     }
-}
 
-impl NetlinkDeserializable<ControlMessage> for ControlMessage {
-    type Error = Compat<DecodeError>;
+    fn group_event(family: &NewFamily, name: &str, id: u32) -> MulticastGroupEvent {
+        MulticastGroupEvent {
+            family_id: family.id.clone(),
+            family_name: family.name.clone(),
+            groups: MulticastGroupList {
+                groups: vec![MulticastGroup {
+                    id: MulticastGroupId(id),
+                    name: MulticastGroupName::new(name),
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn registry_follows_families() {
+        let mut registry = FamilyRegistry::new();
+        let nl80211 = family("nl80211", 0x1a, &[("config", 4)]);
+        let name = nl80211.name.clone();
+
+        registry.handle_event(&ControlMessage::NewFamily(nl80211.clone()));
+        assert_eq!(registry.by_name(&name), Some(&nl80211));
+        assert_eq!(registry.by_id(&FamilyId(0x1a)), Some(&nl80211));
+
+        // Reloaded under a new id
+        let reloaded = family("nl80211", 0x1c, &[("config", 6)]);
+        registry.handle_event(&ControlMessage::NewFamily(reloaded.clone()));
+        assert_eq!(registry.by_name(&name), Some(&reloaded));
+        assert_eq!(registry.by_id(&FamilyId(0x1a)), None);
+        assert_eq!(registry.by_id(&FamilyId(0x1c)), Some(&reloaded));
+
+        registry.handle_event(&ControlMessage::DelFamily(reloaded));
+        assert_eq!(registry.by_name(&name), None);
+        assert_eq!(registry.by_id(&FamilyId(0x1c)), None);
+    }
+
+    #[test]
+    fn registry_drops_family_with_reused_id() {
+        let mut registry = FamilyRegistry::new();
+        registry.handle_event(&ControlMessage::NewFamily(family("nl80211", 0x1a, &[])));
+
+        // The unregistration of nl80211 was missed, and its id went to another family
+        let taskstats = family("TASKSTATS", 0x1a, &[]);
+        registry.handle_event(&ControlMessage::NewFamily(taskstats.clone()));
+
+        assert_eq!(registry.by_name(&FamilyName::new("nl80211")), None);
+        assert_eq!(registry.by_id(&FamilyId(0x1a)), Some(&taskstats));
+    }
+
+    #[test]
+    fn registry_replaces_families() {
+        let mut registry = FamilyRegistry::new();
+        let nl80211 = family("nl80211", 0x1a, &[("config", 4)]);
+        let taskstats = family("TASKSTATS", 0x15, &[]);
+        let devlink = family("devlink", 0x16, &[]);
+        for family in &[&nl80211, &taskstats, &devlink] {
+            registry.handle_event(&ControlMessage::NewFamily((*family).clone()));
+        }
+
+        // nl80211 was reloaded and devlink went away while notifications were lost
+        let reloaded = family("nl80211", 0x1c, &[("config", 6)]);
+        let ethtool = family("ethtool", 0x17, &[]);
+        let mut missed =
+            registry.replace_all(vec![taskstats.clone(), reloaded.clone(), ethtool.clone()]);
+        missed.sort_by_key(|event| match event {
+            ControlMessage::DelFamily(family) => (0, u16::from(family.id.clone())),
+            ControlMessage::NewFamily(family) => (1, u16::from(family.id.clone())),
+            _ => unreachable!(),
+        });
+
+        assert_eq!(
+            missed,
+            vec![
+                ControlMessage::DelFamily(devlink.clone()),
+                ControlMessage::DelFamily(nl80211),
+                ControlMessage::NewFamily(ethtool.clone()),
+                ControlMessage::NewFamily(reloaded.clone()),
+            ]
+        );
+        assert_eq!(registry.by_name(&reloaded.name), Some(&reloaded));
+        assert_eq!(registry.by_name(&devlink.name), None);
+        assert_eq!(registry.by_id(&FamilyId(0x1a)), None);
+        assert_eq!(registry.by_id(&FamilyId(0x17)), Some(&ethtool));
+    }
+
+    #[test]
+    fn registry_follows_multicast_groups() {
+        let mut registry = FamilyRegistry::new();
+        let nl80211 = family("nl80211", 0x1a, &[("config", 4)]);
+        registry.handle_event(&ControlMessage::NewFamily(nl80211.clone()));
+
+        let groups = |registry: &FamilyRegistry| -> Vec<MulticastGroup> {
+            registry
+                .by_name(&nl80211.name)
+                .unwrap()
+                .multicast_groups
+                .iter()
+                .cloned()
+                .collect()
+        };
+
+        registry.handle_event(&ControlMessage::NewMulticastGroup(group_event(
+            &nl80211, "mlme", 7,
+        )));
+        assert_eq!(
+            registry
+                .by_name(&nl80211.name)
+                .and_then(|family| family.multicast_groups.find("mlme"))
+                .map(MulticastGroup::id),
+            Some(MulticastGroupId(7))
+        );
+        assert_eq!(groups(&registry).len(), 2);
+
+        registry.handle_event(&ControlMessage::DelMulticastGroup(group_event(
+            &nl80211, "config", 4,
+        )));
+        assert_eq!(
+            groups(&registry),
+            vec![MulticastGroup {
+                id: MulticastGroupId(7),
+                name: MulticastGroupName::new("mlme"),
+            }]
+        );
 
-    fn deserialize(header: &NetlinkHeader, payload: &[u8]) -> Result<Self, Self::Error> {
-        let generic_buffer = GenericBuffer::new_checked(payload).compat()?;
-        ControlMessage::parse_with_param(&generic_buffer, header.message_type).compat()
+        // Groups of unknown families are ignored
+        let unknown = family("nl802154", 0x1b, &[]);
+        registry.handle_event(&ControlMessage::NewMulticastGroup(group_event(
+            &unknown, "mlme", 8,
+        )));
+        assert_eq!(registry.by_name(&unknown.name), None);
     }
 }
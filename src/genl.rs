@@ -1,10 +1,65 @@
-use netlink_packet_core::DecodeError;
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug},
+    io,
+    marker::PhantomData,
+    os::unix::io::AsRawFd,
+    time::Duration,
+};
 
+use byteorder::{ByteOrder, NativeEndian};
+use failure::{format_err, Compat, Error, ResultExt};
+use netlink_packet_core::{
+    DecodeError, NetlinkDeserializable, NetlinkHeader, NetlinkMessage, NetlinkPayload,
+    NetlinkSerializable, NETLINK_HEADER_LEN, NLM_F_ACK, NLM_F_DUMP, NLM_F_REQUEST,
+};
+use netlink_packet_utils::{nla::Nla, parsers, traits::Emitable};
+use netlink_sys::{Protocol, Socket, SocketAddr};
+
+use crate::controller::{FamilyId, FamilyName, NewFamily};
+
+pub use netlink_packet_utils::nla::NLA_F_NESTED;
+
+/// Implement `Nla` and `Parseable` for a newtype wrapping a single attribute value.
+///
+/// The wrapped type can be anything implementing [`AttributeValue`], such as integers,
+/// `String`, MAC addresses (`[u8; 6]`), opaque binary data (`Vec<u8>`) or a struct of nested
+/// attributes, which is emitted with `NLA_F_NESTED` set. A unit struct without a value type
+/// becomes a zero-length flag attribute, whose presence alone carries the information.
+///
+/// Enums are supported with `Enum as u32: KIND`, encoding them as the given integer type through
+/// `From` conversions in both directions.
 macro_rules! impl_wrapped_attribute {
-    ($attr: ident ($type: tt): $kind: expr $(,)?) => {
+    ($attr: ident: $kind: expr $(,)?) => {
+        impl Nla for $attr {
+            fn value_len(&self) -> usize {
+                0
+            }
+
+            fn kind(&self) -> u16 {
+                $kind
+            }
+
+            fn emit_value(&self, _buffer: &mut [u8]) {}
+        }
+
+        impl<'buffer, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'buffer T>> for $attr {
+            fn parse(buffer: &NlaBuffer<&'buffer T>) -> Result<Self, DecodeError> {
+                match buffer.value() {
+                    [] => Ok($attr),
+                    value => Err(format!(
+                        concat!("invalid flag ", stringify!($attr), ": {:?}"),
+                        value
+                    )
+                    .into()),
+                }
+            }
+        }
+    };
+    ($attr: ident as $repr: ty: $kind: expr $(,)?) => {
         impl Nla for $attr {
             fn value_len(&self) -> usize {
-                impl_wrapped_attribute!(@length($type))(&self.0)
+                std::mem::size_of::<$repr>()
             }
 
             fn kind(&self) -> u16 {
@@ -12,60 +67,266 @@ macro_rules! impl_wrapped_attribute {
             }
 
             fn emit_value(&self, buffer: &mut [u8]) {
-                impl_wrapped_attribute!(@emit(self.0) as $type in buffer)
+                $crate::genl::AttributeValue::emit_value(&<$repr>::from(*self), buffer)
             }
         }
 
         impl<'buffer, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'buffer T>> for $attr {
             fn parse(buffer: &NlaBuffer<&'buffer T>) -> Result<Self, DecodeError> {
-                impl_wrapped_attribute!(@parse(buffer.value()) as $type in $attr)
+                <$repr as $crate::genl::AttributeValue>::parse_value(buffer.value()).map($attr::from)
             }
         }
+    };
+    ($attr: ident ($($type: tt)+): $kind: expr $(,)?) => {
+        impl Nla for $attr {
+            fn value_len(&self) -> usize {
+                $crate::genl::AttributeValue::value_len(&self.0)
+            }
+
+            fn kind(&self) -> u16 {
+                $crate::genl::value_kind::<$($type)+>($kind)
+            }
 
-        impl From<$type> for $attr {
-            fn from(v: $type) -> Self {
+            fn emit_value(&self, buffer: &mut [u8]) {
+                $crate::genl::AttributeValue::emit_value(&self.0, buffer)
+            }
+        }
+
+        impl<'buffer, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'buffer T>> for $attr {
+            fn parse(buffer: &NlaBuffer<&'buffer T>) -> Result<Self, DecodeError> {
+                <$($type)+ as $crate::genl::AttributeValue>::parse_value(buffer.value()).map($attr)
+            }
+        }
+
+        impl From<$($type)+> for $attr {
+            fn from(v: $($type)+) -> Self {
                 Self(v)
             }
         }
 
-        impl From<$attr> for $type {
+        impl From<$attr> for $($type)+ {
             fn from(attr: $attr) -> Self {
-            attr.0
+                attr.0
             }
         }
-
     };
-    (@length(String)) => {
-         |s: &str| { s.len() + 1 }
+}
+
+/// Define an enum mirroring a kernel enum, with `From` conversions to and from its integer type.
+///
+/// Values the kernel may add in the future end up in an extra `Other` variant.
+macro_rules! kernel_enum {
+    (
+        $(#[$meta: meta])*
+        pub enum $name: ident: $repr: ty {
+            $($(#[$variant_meta: meta])* $variant: ident = $value: expr,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)+
+            Other($repr),
+        }
+
+        impl From<$repr> for $name {
+            fn from(value: $repr) -> Self {
+                match value {
+                    $(value if value == $value => Self::$variant,)+
+                    other => Self::Other(other),
+                }
+            }
+        }
+
+        impl From<$name> for $repr {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value,)+
+                    $name::Other(other) => other,
+                }
+            }
+        }
     };
-    (@length($_: tt)) => {
-        std::mem::size_of_val
+}
+
+/// Implement `Nla` and `Parseable` for a list of attribute values nested in attribute `$kind`.
+///
+/// Each entry is emitted as an attribute whose kind is its 1-based position in the list, the
+/// way the kernel lays out arrays such as `CTRL_ATTR_OPS` or `NL80211_ATTR_SCAN_FREQUENCIES`.
+/// Entries can be of any type implementing [`AttributeValue`]. The list and nested entries are
+/// emitted with `NLA_F_NESTED` set.
+macro_rules! impl_attribute_list {
+    ($list: ident { $field: ident: $entry: ty }: $kind: expr $(,)?) => {
+        impl<'buffer, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'buffer T>> for $list {
+            fn parse(buffer: &NlaBuffer<&'buffer T>) -> Result<Self, DecodeError> {
+                let $field = NlasIterator::new(buffer.value())
+                    .map(|attribute: Result<NlaBuffer<_>, _>| {
+                        attribute.and_then(|attr| {
+                            <$entry as $crate::genl::AttributeValue>::parse_value(attr.value())
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Self { $field })
+            }
+        }
+
+        impl Nla for $list {
+            fn value_len(&self) -> usize {
+                $crate::genl::ListEntry::list_len(&self.$field)
+            }
+
+            fn kind(&self) -> u16 {
+                $kind | $crate::genl::NLA_F_NESTED
+            }
+
+            fn emit_value(&self, buffer: &mut [u8]) {
+                $crate::genl::ListEntry::emit_list(&self.$field, buffer)
+            }
+        }
     };
-    (@parse($value: expr) as $type: tt in $attr: ident) => {
-        impl_wrapped_attribute!(@parse($value) as $type).map($attr)
+}
+
+/// Implement `Nla` and `Parseable` for attribute values nested in attribute `$kind` and keyed by
+/// their kind.
+///
+/// This is how the kernel lays out tables such as the bands in `NL80211_ATTR_WIPHY_BANDS`, keyed
+/// by band, or the flags in `NL80211_ATTR_SUPPORTED_IFTYPES`, keyed by interface type. Keys
+/// convert from and into `u32`, values can be of any type implementing [`AttributeValue`]. Like
+/// lists, the map and nested values are emitted with `NLA_F_NESTED` set.
+macro_rules! impl_attribute_map {
+    ($map: ident { $field: ident: $key: ty => $value: ty }: $kind: expr $(,)?) => {
+        impl<'buffer, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'buffer T>> for $map {
+            fn parse(buffer: &NlaBuffer<&'buffer T>) -> Result<Self, DecodeError> {
+                let $field = NlasIterator::new(buffer.value())
+                    .map(|attribute: Result<NlaBuffer<_>, _>| {
+                        attribute.and_then(|attr| {
+                            let key = <$key>::from(u32::from(attr.kind()));
+                            <$value as $crate::genl::AttributeValue>::parse_value(attr.value())
+                                .map(|value| (key, value))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Self { $field })
+            }
+        }
+
+        impl Nla for $map {
+            fn value_len(&self) -> usize {
+                $crate::genl::ListEntry::map_len(&self.$field)
+            }
+
+            fn kind(&self) -> u16 {
+                $kind | $crate::genl::NLA_F_NESTED
+            }
+
+            fn emit_value(&self, buffer: &mut [u8]) {
+                $crate::genl::ListEntry::emit_map(&self.$field, buffer)
+            }
+        }
     };
-    (@parse($value: expr) as u16 ) => {
-        parsers::parse_u16($value)
+}
+
+/// Implement `Parseable`, `Emitable` and [`AttributeValue`] for a struct made up of nested
+/// attributes.
+///
+/// Takes the same field list as [`impl_nested_attribute_parse!`]. Fields are emitted in the
+/// order they are listed. `Option<T>` fields are left out if they are `None`, fields with a
+/// default are left out if they hold it.
+macro_rules! impl_nested_attribute {
+    ($attr: ident, unknown => skip: $($fields: tt)+) => {
+        impl_nested_attribute_parse!($attr, unknown => skip: $($fields)+);
+        impl_nested_attribute!(@emit $attr, $($fields)+);
     };
-    (@parse($value: expr) as u32) => {
-        parsers::parse_u32($value)
+    ($attr: ident: $($fields: tt)+) => {
+        impl_nested_attribute_parse!($attr: $($fields)+);
+        impl_nested_attribute!(@emit $attr, $($fields)+);
     };
-    (@parse($value: expr) as String) => {
-        parsers::parse_string($value)
+    (
+        @emit $attr: ident,
+        $($kind: tt => $field: ident: $type: tt $(<$inner: tt>)? $(= $default: expr)?),+ $(,)?
+    ) => {
+        impl $attr {
+            /// Attributes to emit, in order.
+            #[allow(clippy::vec_init_then_push)]
+            fn present_attributes(&self) -> Vec<&dyn Emitable> {
+                let mut attributes: Vec<&dyn Emitable> = Vec::new();
+                $(
+                    impl_nested_attribute!(
+                        @push(self.$field) into attributes as $type $(<$inner>)? $(= $default)?
+                    );
+                )*
+                attributes
+            }
+        }
+
+        impl Emitable for $attr {
+            fn buffer_len(&self) -> usize {
+                self.present_attributes()
+                    .iter()
+                    .map(|attribute| attribute.buffer_len())
+                    .sum()
+            }
+
+            fn emit(&self, buffer: &mut [u8]) {
+                let mut offset = 0;
+                for attribute in self.present_attributes() {
+                    attribute.emit(&mut buffer[offset..]);
+                    offset += attribute.buffer_len();
+                }
+            }
+        }
+
+        impl $crate::genl::AttributeValue for $attr {
+            const NESTED: bool = true;
+
+            fn value_len(&self) -> usize {
+                self.buffer_len()
+            }
+
+            fn emit_value(&self, buffer: &mut [u8]) {
+                self.emit(buffer)
+            }
+
+            fn parse_value(value: &[u8]) -> Result<Self, DecodeError> {
+                Self::parse(&value)
+            }
+        }
     };
-    (@emit($value: expr) as u16 in $buffer: ident) => {
-        NativeEndian::write_u16($buffer, $value)
+    (@push($field: expr) into $attributes: ident as Option<$inner: tt>) => {
+        if let Some(attribute) = &$field {
+            $attributes.push(attribute);
+        }
     };
-    (@emit($value: expr) as u32 in $buffer: ident) => {
-        NativeEndian::write_u32($buffer, $value)
+    (@push($field: expr) into $attributes: ident as $type: tt = $default: expr) => {
+        if $field != $default {
+            $attributes.push(&$field);
+        }
     };
-    (@emit($value: expr) as String in $buffer: ident) => {
-        $buffer[..$value.len()].copy_from_slice($value.as_bytes())
+    (@push($field: expr) into $attributes: ident as $type: tt) => {
+        $attributes.push(&$field);
     };
 }
 
+/// Implement `Parseable` for a struct made up of nested attributes.
+///
+/// Fields declared as `Option<T>` may be absent, as may fields followed by `= <expr>`, which
+/// then take the value of `<expr>`. All other fields are mandatory. By default an
+/// attribute of an unlisted kind is an error; start with `Struct, unknown => skip:` to ignore
+/// such attributes.
 macro_rules! impl_nested_attribute_parse {
-    ($attr: ident: $($kind: tt => $field: tt: $type: tt),+ $(,)?) => {
+    ($attr: ident, unknown => skip: $($fields: tt)+) => {
+        impl_nested_attribute_parse!(@impl $attr, unknown: skip, $($fields)+);
+    };
+    ($attr: ident: $($fields: tt)+) => {
+        impl_nested_attribute_parse!(@impl $attr, unknown: error, $($fields)+);
+    };
+    (
+        @impl $attr: ident,
+        unknown: $on_unknown: ident,
+        $($kind: tt => $field: ident: $type: tt $(<$inner: tt>)? $(= $default: expr)?),+ $(,)?
+    ) => {
         impl<T: AsRef<[u8]>> Parseable<T> for $attr {
             fn parse(buffer: &T) -> Result<Self, DecodeError> {
                 $(let mut $field = None;)*
@@ -75,39 +336,63 @@ macro_rules! impl_nested_attribute_parse {
                     match attribute.kind() {
                         $(
                             $kind => {
-                                $field.replace($type::parse(&attribute)?);
+                                $field.replace(
+                                    impl_nested_attribute_parse!(@parse(&attribute) as $type $(<$inner>)?)
+                                );
                             }
                         )*
-                        kind => {
-                            return Err(format!(
-                                concat!("encountered unexpected kind {} when parsing ", stringify!($attr)),
-                                kind
-                            )
-                            .into())
-                        }
+                        #[allow(unused_variables)]
+                        kind => impl_nested_attribute_parse!(@unknown($on_unknown) $attr, kind),
                     }
                 }
 
                 Ok(Self {
                     $(
-                        $field: $field
-                            .ok_or_else(||
-                                DecodeError::from(
-                                    concat!(
-                                        "missing attribute ",
-                                        stringify!($kind),
-                                        " when parsing ",
-                                        stringify!($attr)
-                                    )
-                                )
-                            )?
-                    ),*
+                        $field: impl_nested_attribute_parse!(
+                            @finish($field) as $type $(<$inner>)? in $attr, $kind $(, default: $default)?
+                        ),
+                    )*
                 })
             }
         }
     };
+    (@parse($attribute: expr) as Option<$inner: tt>) => {
+        $inner::parse($attribute)?
+    };
+    (@parse($attribute: expr) as $type: tt) => {
+        $type::parse($attribute)?
+    };
+    (@finish($field: ident) as $type: tt in $attr: ident, $kind: tt, default: $default: expr) => {
+        $field.unwrap_or_else(|| $default)
+    };
+    (@finish($field: ident) as Option<$inner: tt> in $attr: ident, $kind: tt) => {
+        $field
+    };
+    (@finish($field: ident) as $type: tt in $attr: ident, $kind: tt) => {
+        $field.ok_or_else(|| {
+            DecodeError::from(concat!(
+                "missing attribute ",
+                stringify!($kind),
+                " when parsing ",
+                stringify!($attr)
+            ))
+        })?
+    };
+    (@unknown(error) $attr: ident, $kind: ident) => {
+        return Err(format!(
+            concat!("encountered unexpected kind {} when parsing ", stringify!($attr)),
+            $kind
+        )
+        .into())
+    };
+    (@unknown(skip) $attr: ident, $kind: ident) => {
+        {}
+    };
 }
 
+/// Length of the generic netlink header preceding the attributes.
+pub const GENL_HDRLEN: usize = 4;
+
 /// GenericBuffer:
 /// 0               1               2               3
 /// 0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7 0 1 2 3 4 5 6 7
@@ -118,28 +403,35 @@ macro_rules! impl_nested_attribute_parse {
 /// |                             ...                             |
 /// |                                                             |
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
-
+///
+/// Can only be created through [`GenericBuffer::new_checked`], which guarantees the buffer holds
+/// a complete header, so none of the accessors can panic.
 pub struct GenericBuffer<T> {
     buffer: T,
 }
 
 impl<T: AsRef<[u8]>> GenericBuffer<T> {
-    pub fn new(buffer: T) -> Self {
-        Self { buffer }
-    }
-
     pub fn length(&self) -> usize {
         self.buffer.as_ref().len()
     }
 
     pub fn new_checked(buffer: T) -> Result<Self, DecodeError> {
-        Ok(Self::new(buffer))
+        let length = buffer.as_ref().len();
+        if length < GENL_HDRLEN {
+            return Err(format!(
+                "generic netlink message too short: {} bytes, header needs {}",
+                length, GENL_HDRLEN
+            )
+            .into());
+        }
+
+        Ok(Self { buffer })
     }
 }
 
 impl<'a, T: AsRef<[u8]> + ?Sized> GenericBuffer<&'a T> {
     pub fn inner(&self) -> &'a [u8] {
-        &self.buffer.as_ref()[..]
+        self.buffer.as_ref()
     }
 
     pub fn command(&self) -> u8 {
@@ -151,12 +443,876 @@ impl<'a, T: AsRef<[u8]> + ?Sized> GenericBuffer<&'a T> {
     }
 
     pub fn attributes(&self) -> &'a [u8] {
-        &self.inner()[4..]
+        &self.inner()[GENL_HDRLEN..]
     }
 }
 
-impl<'a, T: AsRef<[u8]> + AsMut<[u8]> + ?Sized> GenericBuffer<&'a mut T> {
+impl<T: AsRef<[u8]> + AsMut<[u8]> + ?Sized> GenericBuffer<&mut T> {
     pub fn inner_mut(&mut self) -> &mut [u8] {
         &mut self.buffer.as_mut()[..]
     }
+
+    pub fn set_command(&mut self, command: u8) {
+        self.inner_mut()[0] = command
+    }
+
+    pub fn set_version(&mut self, version: u8) {
+        self.inner_mut()[1] = version
+    }
+
+    pub fn set_reserved(&mut self, reserved: u16) {
+        NativeEndian::write_u16(&mut self.inner_mut()[2..GENL_HDRLEN], reserved)
+    }
+
+    pub fn attributes_mut(&mut self) -> &mut [u8] {
+        &mut self.inner_mut()[GENL_HDRLEN..]
+    }
+}
+
+/// A type that can be the value of an attribute.
+///
+/// This is what lets the attribute macros treat integers, strings, binary blobs and nested
+/// attribute sets alike.
+pub trait AttributeValue: Sized {
+    /// Whether values are sets of attributes themselves, which are flagged with `NLA_F_NESTED`.
+    const NESTED: bool = false;
+
+    fn value_len(&self) -> usize;
+
+    fn emit_value(&self, buffer: &mut [u8]);
+
+    fn parse_value(value: &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// `kind` with `NLA_F_NESTED` set if values of type `T` are sets of attributes.
+pub const fn value_kind<T: AttributeValue>(kind: u16) -> u16 {
+    if T::NESTED {
+        kind | NLA_F_NESTED
+    } else {
+        kind
+    }
+}
+
+macro_rules! impl_integer_attribute_value {
+    ($($type: ty => $parse: expr, $write: expr;)+) => {
+        $(
+            impl AttributeValue for $type {
+                fn value_len(&self) -> usize {
+                    std::mem::size_of::<$type>()
+                }
+
+                fn emit_value(&self, buffer: &mut [u8]) {
+                    $write(buffer, *self)
+                }
+
+                fn parse_value(value: &[u8]) -> Result<Self, DecodeError> {
+                    $parse(value)
+                }
+            }
+        )+
+    };
+}
+
+impl_integer_attribute_value! {
+    u8 => parsers::parse_u8, |buffer: &mut [u8], value| buffer[0] = value;
+    i8 => |value| parsers::parse_u8(value).map(|value| value as i8),
+        |buffer: &mut [u8], value| buffer[0] = value as u8;
+    u16 => parsers::parse_u16, NativeEndian::write_u16;
+    u32 => parsers::parse_u32, NativeEndian::write_u32;
+    u64 => parsers::parse_u64, NativeEndian::write_u64;
+    i32 => parsers::parse_i32, NativeEndian::write_i32;
+}
+
+impl AttributeValue for String {
+    fn value_len(&self) -> usize {
+        self.len() + 1
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        buffer[..self.len()].copy_from_slice(self.as_bytes());
+        buffer[self.len()] = 0;
+    }
+
+    fn parse_value(value: &[u8]) -> Result<Self, DecodeError> {
+        parsers::parse_string(value)
+    }
+}
+
+impl AttributeValue for [u8; 6] {
+    fn value_len(&self) -> usize {
+        self.len()
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        buffer.copy_from_slice(self)
+    }
+
+    fn parse_value(value: &[u8]) -> Result<Self, DecodeError> {
+        parsers::parse_mac(value)
+    }
+}
+
+impl AttributeValue for Vec<u8> {
+    fn value_len(&self) -> usize {
+        self.len()
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        buffer.copy_from_slice(self)
+    }
+
+    fn parse_value(value: &[u8]) -> Result<Self, DecodeError> {
+        Ok(value.to_vec())
+    }
+}
+
+/// The value of a flag attribute, which carries no data.
+impl AttributeValue for () {
+    fn value_len(&self) -> usize {
+        0
+    }
+
+    fn emit_value(&self, _buffer: &mut [u8]) {}
+
+    fn parse_value(value: &[u8]) -> Result<Self, DecodeError> {
+        match value {
+            [] => Ok(()),
+            value => Err(format!("invalid flag: {:?}", value).into()),
+        }
+    }
+}
+
+/// An array of `u32`s packed into a single attribute, such as `NL80211_ATTR_CIPHER_SUITES`.
+impl AttributeValue for Vec<u32> {
+    fn value_len(&self) -> usize {
+        self.len() * std::mem::size_of::<u32>()
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        NativeEndian::write_u32_into(self, buffer)
+    }
+
+    fn parse_value(value: &[u8]) -> Result<Self, DecodeError> {
+        value
+            .chunks(std::mem::size_of::<u32>())
+            .map(parsers::parse_u32)
+            .collect()
+    }
+}
+
+/// An entry of a nested attribute list, keyed by its 1-based position in the list.
+pub struct ListEntry<'a, T>(pub u16, pub &'a T);
+
+impl<'a, T: AttributeValue> Nla for ListEntry<'a, T> {
+    fn value_len(&self) -> usize {
+        self.1.value_len()
+    }
+
+    fn kind(&self) -> u16 {
+        value_kind::<T>(self.0)
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        self.1.emit_value(buffer)
+    }
+}
+
+impl<'a, T: AttributeValue> ListEntry<'a, T> {
+    fn entries(list: &'a [T]) -> impl Iterator<Item = ListEntry<'a, T>> {
+        list.iter()
+            .enumerate()
+            .map(|(index, entry)| ListEntry(index as u16 + 1, entry))
+    }
+
+    fn keyed_entries<K: Copy + Into<u32>>(
+        map: &'a [(K, T)],
+    ) -> impl Iterator<Item = ListEntry<'a, T>> {
+        map.iter()
+            .map(|(key, entry)| ListEntry((*key).into() as u16, entry))
+    }
+
+    fn emit_entries<I: Iterator<Item = ListEntry<'a, T>>>(entries: I, buffer: &mut [u8]) {
+        let mut offset = 0;
+        for entry in entries {
+            entry.emit(&mut buffer[offset..]);
+            offset += entry.buffer_len();
+        }
+    }
+
+    /// Length of `list` when emitted as a sequence of entries, including padding.
+    pub fn list_len(list: &'a [T]) -> usize {
+        Self::entries(list).map(|entry| entry.buffer_len()).sum()
+    }
+
+    pub fn emit_list(list: &'a [T], buffer: &mut [u8]) {
+        Self::emit_entries(Self::entries(list), buffer)
+    }
+
+    /// Length of `map` when emitted as a sequence of entries keyed by kind, including padding.
+    pub fn map_len<K: Copy + Into<u32>>(map: &'a [(K, T)]) -> usize {
+        Self::keyed_entries(map)
+            .map(|entry| entry.buffer_len())
+            .sum()
+    }
+
+    pub fn emit_map<K: Copy + Into<u32>>(map: &'a [(K, T)], buffer: &mut [u8]) {
+        Self::emit_entries(Self::keyed_entries(map), buffer)
+    }
+}
+
+/// An already serialized attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawAttribute {
+    kind: u16,
+    value: Vec<u8>,
+}
+
+impl RawAttribute {
+    pub fn new(kind: u16, value: Vec<u8>) -> Self {
+        Self { kind, value }
+    }
+
+    pub fn u16(kind: u16, value: u16) -> Self {
+        Self::new(kind, value.to_ne_bytes().to_vec())
+    }
+
+    pub fn u32(kind: u16, value: u32) -> Self {
+        Self::new(kind, value.to_ne_bytes().to_vec())
+    }
+
+    /// An attribute with `NLA_F_NESTED` set, containing `children`.
+    pub fn nested(kind: u16, children: &[RawAttribute]) -> Self {
+        let mut value = vec![0; children.buffer_len()];
+        children.emit(&mut value);
+        Self::new(kind | NLA_F_NESTED, value)
+    }
+}
+
+impl Nla for RawAttribute {
+    fn value_len(&self) -> usize {
+        self.value.len()
+    }
+
+    fn kind(&self) -> u16 {
+        self.kind
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        buffer.copy_from_slice(&self.value)
+    }
+}
+
+/// Commands of a generic netlink family along with their attributes.
+///
+/// Usually implemented with `#[derive(GenlMessage)]`.
+pub trait GenlPayload: Debug + PartialEq + Eq + Clone {
+    fn command(&self) -> u8;
+
+    fn version(&self) -> u8;
+
+    fn attribute_size(&self) -> usize;
+
+    fn emit_attributes(&self, buffer: &mut [u8]);
+
+    fn parse(buffer: &GenericBuffer<&[u8]>) -> Result<Self, DecodeError>;
+}
+
+/// A generic netlink family, made up of its registered name, the id the kernel assigned to it
+/// and the messages it understands.
+pub trait GenlFamily: Sized {
+    type Message: GenlPayload;
+
+    fn name(&self) -> FamilyName;
+
+    fn id(&self) -> FamilyId;
+
+    /// Address `message` to this family.
+    fn tag_message(&self, message: Self::Message) -> GenlMessage<Self> {
+        GenlMessage::new(self.id(), message)
+    }
+}
+
+/// A message of family `F`, tagged with the family's id as the netlink message type.
+pub struct GenlMessage<F: GenlFamily> {
+    pub family_id: FamilyId,
+    pub payload: F::Message,
+    family: PhantomData<F>,
+}
+
+impl<F: GenlFamily> GenlMessage<F> {
+    pub fn new(family_id: FamilyId, payload: F::Message) -> Self {
+        Self {
+            family_id,
+            payload,
+            family: PhantomData,
+        }
+    }
+}
+
+// Implemented by hand, deriving would needlessly require the family itself to implement these.
+impl<F: GenlFamily> Debug for GenlMessage<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GenlMessage")
+            .field("family_id", &self.family_id)
+            .field("payload", &self.payload)
+            .finish()
+    }
+}
+
+impl<F: GenlFamily> Clone for GenlMessage<F> {
+    fn clone(&self) -> Self {
+        Self::new(self.family_id.clone(), self.payload.clone())
+    }
+}
+
+impl<F: GenlFamily> PartialEq for GenlMessage<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.family_id == other.family_id && self.payload == other.payload
+    }
+}
+
+impl<F: GenlFamily> Eq for GenlMessage<F> {}
+
+impl<F: GenlFamily> NetlinkSerializable<GenlMessage<F>> for GenlMessage<F> {
+    fn buffer_len(&self) -> usize {
+        GENL_HDRLEN + self.payload.attribute_size()
+    }
+
+    fn message_type(&self) -> u16 {
+        self.family_id.clone().into()
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) {
+        // netlink-packet-core sizes the buffer with buffer_len(), so anything shorter is a bug
+        assert!(
+            buffer.len() >= self.buffer_len(),
+            "buffer of {} bytes too short for a {} byte message",
+            buffer.len(),
+            self.buffer_len()
+        );
+        let mut buffer = GenericBuffer::new_checked(buffer).expect("checked above");
+
+        buffer.set_command(self.payload.command());
+        buffer.set_version(self.payload.version());
+        buffer.set_reserved(0);
+        self.payload.emit_attributes(buffer.attributes_mut());
+    }
+}
+
+impl<F: GenlFamily> NetlinkDeserializable<GenlMessage<F>> for GenlMessage<F> {
+    type Error = Compat<DecodeError>;
+
+    fn deserialize(header: &NetlinkHeader, payload: &[u8]) -> Result<Self, Self::Error> {
+        let buffer = GenericBuffer::new_checked(payload).compat()?;
+        let payload = F::Message::parse(&buffer).compat()?;
+
+        Ok(Self::new(header.message_type.into(), payload))
+    }
+}
+
+impl<F: GenlFamily> From<GenlMessage<F>> for NetlinkPayload<GenlMessage<F>> {
+    fn from(message: GenlMessage<F>) -> Self {
+        NetlinkPayload::InnerMessage(message)
+    }
+}
+
+/// Size of the buffer replies and notifications from the kernel are received into.
+///
+/// Multipart dumps fill up to a page (or more) per datagram, so this errs on the large side.
+pub const RECEIVE_BUFFER_SIZE: usize = 32 * 1024;
+
+/// Serialize `message` with the given netlink header `flags` and send it to the kernel.
+pub fn send<M>(socket: &Socket, message: M, flags: u16) -> Result<(), Error>
+where
+    M: NetlinkSerializable<M> + Into<NetlinkPayload<M>> + Debug + PartialEq + Eq + Clone,
+{
+    let mut packet = NetlinkMessage::from(message);
+    packet.header.flags = flags;
+    packet.finalize();
+
+    let mut buf = vec![0; packet.header.length as usize];
+    packet.serialize(&mut buf);
+
+    socket.send_to(&buf, &SocketAddr::new(0, 0), 0)?;
+    Ok(())
+}
+
+/// Receive a single datagram and parse every netlink message it carries.
+///
+/// Multipart replies and multicast notifications often pack several messages into one datagram.
+/// A message that fails to parse does not affect the ones following it.
+pub fn receive<M>(
+    socket: &Socket,
+    buffer: &mut [u8],
+) -> Result<Vec<Result<NetlinkMessage<M>, DecodeError>>, Error>
+where
+    M: NetlinkDeserializable<M> + Debug + PartialEq + Eq + Clone,
+{
+    let (n, _addr) = socket.recv_from(buffer, 0)?;
+    let mut datagram = &buffer[..n];
+    let mut messages = Vec::new();
+
+    while datagram.len() >= NETLINK_HEADER_LEN {
+        let length = NativeEndian::read_u32(datagram) as usize;
+        if length < NETLINK_HEADER_LEN {
+            messages.push(Err(
+                format!("invalid netlink message length {}", length).into()
+            ));
+            break;
+        }
+
+        messages.push(NetlinkMessage::<M>::deserialize(datagram));
+
+        // Messages are aligned to NLMSG_ALIGNTO (4 bytes)
+        let aligned = (length + 3) & !3;
+        datagram = &datagram[aligned.min(datagram.len())..];
+    }
+
+    Ok(messages)
+}
+
+fn error_from_code(code: i32) -> Error {
+    io::Error::from_raw_os_error(-code).into()
+}
+
+/// Send `message` as a request and wait for the kernel's reply.
+pub fn request<F: GenlFamily>(
+    socket: &Socket,
+    message: GenlMessage<F>,
+) -> Result<F::Message, Error> {
+    send(socket, message, NLM_F_REQUEST)?;
+
+    let mut recv_buf = vec![0; RECEIVE_BUFFER_SIZE];
+
+    loop {
+        for response in receive::<GenlMessage<F>>(socket, &mut recv_buf)? {
+            match response?.payload {
+                NetlinkPayload::InnerMessage(reply) => return Ok(reply.payload),
+                NetlinkPayload::Error(error) => return Err(error_from_code(error.code)),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Send `message` as a request the kernel does not reply to, and wait for its acknowledgement.
+pub fn execute<F: GenlFamily>(socket: &Socket, message: GenlMessage<F>) -> Result<(), Error> {
+    send(socket, message, NLM_F_REQUEST | NLM_F_ACK)?;
+
+    let mut recv_buf = vec![0; RECEIVE_BUFFER_SIZE];
+
+    loop {
+        for response in receive::<GenlMessage<F>>(socket, &mut recv_buf)? {
+            match response?.payload {
+                NetlinkPayload::Ack(_) => return Ok(()),
+                NetlinkPayload::Error(error) => return Err(error_from_code(error.code)),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Send `message` as a dump request and collect every reply until the kernel signals
+/// `NLMSG_DONE`.
+pub fn dump<F: GenlFamily>(
+    socket: &Socket,
+    message: GenlMessage<F>,
+) -> Result<Vec<F::Message>, Error> {
+    send(socket, message, NLM_F_REQUEST | NLM_F_DUMP)?;
+
+    let mut replies = Vec::new();
+    let mut recv_buf = vec![0; RECEIVE_BUFFER_SIZE];
+
+    // Keep reading up to NLMSG_DONE even if a reply is malformed, so the rest of the dump does
+    // not end up as the reply to the next request on this socket.
+    let mut decode_error = None;
+
+    loop {
+        for response in receive::<GenlMessage<F>>(socket, &mut recv_buf)? {
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    decode_error.get_or_insert(e);
+                    continue;
+                }
+            };
+
+            match response.payload {
+                NetlinkPayload::InnerMessage(message) => replies.push(message.payload),
+                NetlinkPayload::Done => {
+                    return match decode_error {
+                        Some(e) => Err(e.into()),
+                        None => Ok(replies),
+                    }
+                }
+                NetlinkPayload::Error(error) => return Err(error_from_code(error.code)),
+                NetlinkPayload::Overrun(_) => {
+                    return Err(failure::err_msg("dump was interrupted by an overrun"))
+                }
+                NetlinkPayload::Ack(_) | NetlinkPayload::Noop => {}
+            }
+        }
+    }
+}
+
+/// Listens for the notifications of family `F` on a set of its multicast groups.
+///
+/// Uses its own socket so notifications do not interleave with replies to requests.
+pub struct Notifications<F: GenlFamily> {
+    socket: Socket,
+    recv_buf: Vec<u8>,
+    pending: VecDeque<Result<F::Message, DecodeError>>,
+}
+
+impl<F: GenlFamily> Notifications<F> {
+    /// Subscribe to the multicast groups of `family` called `groups`.
+    pub fn new(family: &NewFamily, groups: &[&str]) -> Result<Self, Error> {
+        let mut socket = Socket::new(Protocol::Generic)?;
+        // Bind right away, so unicast notifications can be addressed to the socket
+        socket.bind_auto()?;
+
+        for name in groups {
+            let group = family.multicast_groups.find(name).ok_or_else(|| {
+                format_err!(
+                    "{} has no \"{}\" multicast group",
+                    family.name.as_str(),
+                    name
+                )
+            })?;
+            socket.add_membership(group.id().into())?;
+        }
+
+        Ok(Self {
+            socket,
+            recv_buf: vec![0; RECEIVE_BUFFER_SIZE],
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// The socket notifications are received on, to register for unicast ones such as frames.
+    ///
+    /// Waiting for the reply to a request on it drops notifications received in the meantime,
+    /// so send requests before relying on notifications.
+    pub fn socket(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Block until the next notification arrives.
+    ///
+    /// Notifications with commands `F::Message` does not parse fail with a `DecodeError`.
+    pub fn next_event(&mut self) -> Result<F::Message, Error> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return event.map_err(Error::from);
+            }
+
+            self.receive()?;
+        }
+    }
+
+    /// Remove the queued notifications `take` selects, keeping the order of the rest.
+    pub fn take_pending<P>(&mut self, mut take: P) -> Vec<F::Message>
+    where
+        P: FnMut(&F::Message) -> bool,
+    {
+        let mut taken = Vec::new();
+        for event in std::mem::take(&mut self.pending) {
+            match event {
+                Ok(event) if take(&event) => taken.push(event),
+                event => self.pending.push_back(event),
+            }
+        }
+
+        taken
+    }
+
+    /// Wait up to `timeout` for a datagram and queue the notifications it carries. Returns
+    /// whether one arrived.
+    pub fn receive_within(&mut self, timeout: Duration) -> Result<bool, Error> {
+        let mut fd = libc::pollfd {
+            fd: self.socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // Round up, so a timeout under a millisecond does not become a busy loop
+        let millis = timeout.as_micros().div_ceil(1000);
+        let millis = millis.min(libc::c_int::MAX as u128) as libc::c_int;
+
+        match unsafe { libc::poll(&mut fd, 1, millis) } {
+            -1 => Err(io::Error::last_os_error().into()),
+            0 => Ok(false),
+            _ => self.receive().map(|()| true),
+        }
+    }
+
+    /// Receive a datagram and queue the notifications it carries.
+    fn receive(&mut self) -> Result<(), Error> {
+        for message in receive::<GenlMessage<F>>(&self.socket, &mut self.recv_buf)? {
+            match message.map(|message| message.payload) {
+                Ok(NetlinkPayload::InnerMessage(message)) => {
+                    self.pending.push_back(Ok(message.payload))
+                }
+                Ok(_) => {}
+                Err(e) => self.pending.push_back(Err(e)),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<F: GenlFamily> Iterator for Notifications<F> {
+    type Item = Result<F::Message, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_event())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use netlink_packet_utils::{
+        nla::{NlaBuffer, NlasIterator},
+        traits::Parseable,
+    };
+
+    use super::*;
+    use crate::controller::{ControlFamily, ControlMessage};
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Byte(u8);
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Name(String);
+
+    impl_wrapped_attribute!(Byte(u8): 1);
+    impl_wrapped_attribute!(Name(String): 2);
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Inner {
+        byte: Byte,
+        name: Option<Name>,
+    }
+
+    impl_nested_attribute! {
+        Inner:
+            1 => byte: Byte,
+            2 => name: Option<Name>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Outer(Inner);
+
+    impl_wrapped_attribute!(Outer(Inner): 3);
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Bytes {
+        bytes: Vec<u8>,
+    }
+
+    impl_attribute_list!(Bytes { bytes: u8 }: 4);
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Table {
+        entries: Vec<(u32, Inner)>,
+    }
+
+    impl_attribute_map!(Table { entries: u32 => Inner }: 5);
+
+    #[derive(Debug, Clone, PartialEq, Eq, cecd_derive::NlaSet)]
+    enum Setting {
+        #[nla(kind = 1)]
+        Enabled,
+        #[nla(kind = 2)]
+        Inner(Inner),
+        #[nla(unknown)]
+        Other(u16, Vec<u8>),
+    }
+
+    fn emit<T: Emitable>(attribute: &T) -> Vec<u8> {
+        let mut buffer = vec![0xff; attribute.buffer_len()];
+        attribute.emit(&mut buffer);
+        buffer
+    }
+
+    fn parse<T>(bytes: &[u8]) -> T
+    where
+        T: for<'a> Parseable<NlaBuffer<&'a [u8]>>,
+    {
+        T::parse(&NlaBuffer::new_checked(bytes).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn odd_length_values_are_padded() {
+        assert_eq!(emit(&Byte(0xab)), [5, 0, 1, 0, 0xab, 0, 0, 0]);
+        assert_eq!(
+            emit(&Name("abcd".to_owned())),
+            [9, 0, 2, 0, b'a', b'b', b'c', b'd', 0, 0, 0, 0]
+        );
+        assert_eq!(
+            emit(&Name("abc".to_owned())),
+            [8, 0, 2, 0, b'a', b'b', b'c', 0]
+        );
+    }
+
+    fn round_trip<T: AttributeValue + Debug + PartialEq>(value: T, expected: &[u8]) {
+        let mut buffer = vec![0; value.value_len()];
+        value.emit_value(&mut buffer);
+        assert_eq!(buffer, expected);
+        assert_eq!(T::parse_value(&buffer).unwrap(), value);
+    }
+
+    /// Check that values of type `T` only parse from exactly `length` bytes.
+    fn only_parses_length<T: AttributeValue + Debug>(length: usize) {
+        for other in (0..length + 2).filter(|other| *other != length) {
+            let value = vec![0; other];
+            assert!(
+                T::parse_value(&value).is_err(),
+                "parsed {} bytes as {}",
+                other,
+                std::any::type_name::<T>()
+            );
+        }
+        assert!(T::parse_value(&vec![0; length]).is_ok());
+    }
+
+    #[test]
+    fn fixed_size_values_round_trip() {
+        round_trip(0x0102_0304_0506_0708u64, &[8, 7, 6, 5, 4, 3, 2, 1]);
+        round_trip(-2i32, &[0xfe, 0xff, 0xff, 0xff]);
+        round_trip((), &[]);
+        round_trip(
+            [0x02, 0x00, 0x00, 0x00, 0x01, 0x00],
+            &[0x02, 0x00, 0x00, 0x00, 0x01, 0x00],
+        );
+    }
+
+    #[test]
+    fn fixed_size_values_reject_other_lengths() {
+        only_parses_length::<u64>(8);
+        only_parses_length::<i32>(4);
+        only_parses_length::<()>(0);
+        only_parses_length::<[u8; 6]>(6);
+    }
+
+    #[test]
+    fn wrapped_nested_attribute_sets_flag() {
+        let outer = Outer(Inner {
+            byte: Byte(1),
+            name: Some(Name("a".to_owned())),
+        });
+
+        #[rustfmt::skip]
+        let expected = [
+            20, 0, 3, 0x80,
+                5, 0, 1, 0, 1, 0, 0, 0,
+                6, 0, 2, 0, b'a', 0, 0, 0,
+        ];
+        assert_eq!(emit(&outer), expected);
+        assert_eq!(parse::<Outer>(&expected), outer);
+    }
+
+    #[test]
+    fn list_sets_flag_on_list_only() {
+        let list = Bytes { bytes: vec![1, 2] };
+
+        #[rustfmt::skip]
+        let expected = [
+            20, 0, 4, 0x80,
+                5, 0, 1, 0, 1, 0, 0, 0,
+                5, 0, 2, 0, 2, 0, 0, 0,
+        ];
+        assert_eq!(emit(&list), expected);
+        assert_eq!(parse::<Bytes>(&expected), list);
+    }
+
+    #[test]
+    fn map_sets_flag_on_nested_values() {
+        let table = Table {
+            entries: vec![(
+                7,
+                Inner {
+                    byte: Byte(9),
+                    name: None,
+                },
+            )],
+        };
+
+        #[rustfmt::skip]
+        let expected = [
+            16, 0, 5, 0x80,
+                12, 0, 7, 0x80,
+                    5, 0, 1, 0, 9, 0, 0, 0,
+        ];
+        assert_eq!(emit(&table), expected);
+        assert_eq!(parse::<Table>(&expected), table);
+
+        let entry = NlasIterator::new(&expected[4..]).next().unwrap().unwrap();
+        assert!(entry.nested_flag());
+        assert_eq!(entry.kind(), 7);
+    }
+
+    #[test]
+    fn derived_set_round_trip() {
+        assert_eq!(emit(&Setting::Enabled), [4, 0, 1, 0]);
+        assert_eq!(parse::<Setting>(&[4, 0, 1, 0]), Setting::Enabled);
+
+        let inner = Setting::Inner(Inner {
+            byte: Byte(3),
+            name: None,
+        });
+        #[rustfmt::skip]
+        let mut expected = [
+            12, 0, 2, 0x80,
+                5, 0, 1, 0, 3, 0, 0, 0,
+        ];
+        assert_eq!(emit(&inner), expected);
+        assert_eq!(parse::<Setting>(&expected), inner);
+        // As sent by kernels starting nests without the flag
+        expected[3] = 0;
+        assert_eq!(parse::<Setting>(&expected), inner);
+
+        let other = [6, 0, 9, 0, 0xaa, 0xbb, 0, 0];
+        assert_eq!(
+            parse::<Setting>(&other),
+            Setting::Other(9, vec![0xaa, 0xbb])
+        );
+        assert_eq!(emit(&Setting::Other(9, vec![0xaa, 0xbb])), other);
+    }
+
+    #[test]
+    fn short_buffers_are_rejected() {
+        for length in 0..GENL_HDRLEN {
+            assert!(GenericBuffer::new_checked(&[0; GENL_HDRLEN][..length]).is_err());
+        }
+        assert!(GenericBuffer::new_checked(&[0; GENL_HDRLEN][..]).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "too short")]
+    fn serializing_into_short_buffer_panics() {
+        let message =
+            ControlFamily.tag_message(ControlMessage::GetFamily(FamilyName::new("nl80211")));
+        message.serialize(&mut [0; GENL_HDRLEN]);
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let attribute = emit(&Byte(0x42));
+        let mut payload = vec![0xff; GENL_HDRLEN + attribute.len()];
+
+        let mut buffer = GenericBuffer::new_checked(&mut payload[..]).unwrap();
+        buffer.set_command(7);
+        buffer.set_version(2);
+        buffer.set_reserved(0);
+        buffer.attributes_mut().copy_from_slice(&attribute);
+
+        assert_eq!(payload[..GENL_HDRLEN], [7, 2, 0, 0]);
+
+        let buffer = GenericBuffer::new_checked(&payload[..]).unwrap();
+        assert_eq!(buffer.command(), 7);
+        assert_eq!(buffer.version(), 2);
+        assert_eq!(buffer.attributes(), &attribute[..]);
+        assert_eq!(parse::<Byte>(buffer.attributes()), Byte(0x42));
+    }
 }
@@ -0,0 +1,136 @@
+//! Just enough of IEEE 802.11 to build and inspect the management frames of an access point.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+/// Frame control of beacons.
+pub const FRAME_TYPE_BEACON: u16 = 0x0080;
+
+/// Frame control of probe requests, which consoles look for StreetPass peers with.
+pub const FRAME_TYPE_PROBE_REQUEST: u16 = 0x0040;
+
+pub const FRAME_TYPE_PROBE_RESPONSE: u16 = 0x0050;
+
+/// Frame control of action frames, which StreetPass peers exchange their data in.
+pub const FRAME_TYPE_ACTION: u16 = 0x00d0;
+
+pub const BROADCAST: [u8; 6] = [0xff; 6];
+
+/// Reason code for deauthenticating stations because the access point is going away.
+pub const REASON_DEAUTH_LEAVING: u16 = 3;
+
+/// Length of the header of management frames.
+pub const HEADER_LEN: usize = 24;
+
+const ELEMENT_SSID: u8 = 0;
+const ELEMENT_SUPPORTED_RATES: u8 = 1;
+const ELEMENT_DS_PARAMETER_SET: u8 = 3;
+
+const CAPABILITY_ESS: u16 = 0x0001;
+const CAPABILITY_SHORT_PREAMBLE: u16 = 0x0020;
+
+/// 1, 2, 5.5 and 11 Mbps as basic rates, 18, 24, 36 and 54 Mbps as supported ones.
+const SUPPORTED_RATES: [u8; 8] = [0x82, 0x84, 0x8b, 0x96, 0x24, 0x30, 0x48, 0x6c];
+
+/// Settings of the BSS advertised in beacons and probe responses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BssSettings {
+    pub bssid: [u8; 6],
+    pub ssid: Vec<u8>,
+    /// Beacon interval in time units of 1024 microseconds.
+    pub beacon_interval: u16,
+    /// Frequency of the 2.4 GHz channel in MHz.
+    pub frequency: u32,
+}
+
+impl BssSettings {
+    /// Beacon up to the TIM element, which the kernel inserts.
+    pub fn beacon_head(&self) -> Vec<u8> {
+        self.frame(FRAME_TYPE_BEACON, BROADCAST)
+    }
+
+    /// Response to a probe request from `destination`.
+    pub fn probe_response(&self, destination: [u8; 6]) -> Vec<u8> {
+        self.frame(FRAME_TYPE_PROBE_RESPONSE, destination)
+    }
+
+    /// Whether a probe request for `ssid` is meant for this BSS.
+    pub fn matches(&self, ssid: &[u8]) -> bool {
+        ssid.is_empty() || ssid == self.ssid.as_slice()
+    }
+
+    fn frame(&self, frame_control: u16, destination: [u8; 6]) -> Vec<u8> {
+        let mut frame = header(frame_control, destination, self.bssid, self.bssid);
+
+        // Timestamp, filled in by the hardware
+        frame.extend_from_slice(&[0; 8]);
+        let mut fixed = [0; 4];
+        LittleEndian::write_u16(&mut fixed[..2], self.beacon_interval);
+        LittleEndian::write_u16(&mut fixed[2..], CAPABILITY_ESS | CAPABILITY_SHORT_PREAMBLE);
+        frame.extend_from_slice(&fixed);
+
+        frame.extend(element(ELEMENT_SSID, &self.ssid));
+        frame.extend(element(ELEMENT_SUPPORTED_RATES, &SUPPORTED_RATES));
+        frame.extend(element(
+            ELEMENT_DS_PARAMETER_SET,
+            &[channel_number(self.frequency)],
+        ));
+
+        frame
+    }
+}
+
+/// Header of a management frame, with duration and sequence number left to the kernel.
+pub fn header(
+    frame_control: u16,
+    destination: [u8; 6],
+    source: [u8; 6],
+    bssid: [u8; 6],
+) -> Vec<u8> {
+    let mut header = vec![0; HEADER_LEN];
+    LittleEndian::write_u16(&mut header[..2], frame_control);
+    header[4..10].copy_from_slice(&destination);
+    header[10..16].copy_from_slice(&source);
+    header[16..22].copy_from_slice(&bssid);
+    header
+}
+
+/// An information element with the given id.
+pub fn element(id: u8, body: &[u8]) -> Vec<u8> {
+    let mut element = Vec::with_capacity(2 + body.len());
+    element.push(id);
+    element.push(body.len() as u8);
+    element.extend_from_slice(body);
+    element
+}
+
+/// Frame control of `frame` with only type and subtype set.
+pub fn frame_type(frame: &[u8]) -> Option<u16> {
+    if frame.len() < HEADER_LEN {
+        return None;
+    }
+
+    Some(LittleEndian::read_u16(frame) & 0x00fc)
+}
+
+/// Transmitter of `frame`.
+pub fn source(frame: &[u8]) -> Option<[u8; 6]> {
+    let mut source = [0; 6];
+    source.copy_from_slice(frame.get(10..16)?);
+    Some(source)
+}
+
+/// SSID a probe request is looking for, which is empty for any network.
+pub fn probe_request_ssid(frame: &[u8]) -> Option<&[u8]> {
+    match frame.get(HEADER_LEN..)? {
+        [ELEMENT_SSID, length, rest @ ..] => rest.get(..*length as usize),
+        _ => None,
+    }
+}
+
+/// Channel number of the 2.4 GHz channel at `mhz`.
+fn channel_number(mhz: u32) -> u8 {
+    match mhz {
+        2484 => 14,
+        mhz => ((mhz.saturating_sub(2407)) / 5) as u8,
+    }
+}
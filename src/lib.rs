@@ -0,0 +1,8 @@
+//! Generic netlink, nl80211 and IEEE 802.11 support for relaying StreetPass.
+
+#[macro_use]
+pub mod genl;
+
+pub mod controller;
+pub mod ieee80211;
+pub mod nl80211;
@@ -1,112 +1,822 @@
-#[macro_use]
-mod genl;
+use cecd::{
+    controller::{ControlFamily, ControlMessage, FamilyRegistry, NewFamily},
+    genl::{GenlFamily, Notifications},
+    ieee80211::{self, BssSettings, FRAME_TYPE_ACTION, FRAME_TYPE_PROBE_REQUEST},
+    nl80211::{
+        ApBeaconInterval, AuthType, BeaconHead, ChannelType, DelStation, DtimPeriod, Frame,
+        FrameInfo, FrameMatch, FrameType, Frequency, HiddenSsid, Interface, InterfaceChanges,
+        InterfaceIndex, InterfaceName, InterfaceType, MonitorFlag, MonitorFlags, NewInterface,
+        Nl80221Family, Nl80221Message, ProbeResponse, RawFrame, ReasonCode, RegisterFrame,
+        ScanFrequencies, ScanSsids, SetChannel, Ssid, StartAp, StationInfo, TriggerScan, Wiphy,
+        NL80211_CMD_FRAME, NL80211_CMD_REGISTER_FRAME, NL80211_FAMILY_NAME,
+        NL80211_MULTICAST_GROUP_MLME,
+    },
+};
 
-mod constants;
-mod controller;
-mod nl80211;
+use netlink_packet_utils::DecodeError;
+use netlink_sys::{Protocol, Socket};
 
-use crate::{
-    controller::{ControlMessage, FamilyName, NewFamily},
-    nl80211::{Nl80221Family, Nl80221Message},
+use std::{
+    io::{Error, Result},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
 };
 
-use netlink_packet_core::{
-    NetlinkMessage, NetlinkPayload, NLM_F_ACK, NLM_F_MATCH, NLM_F_REQUEST, NLM_F_ROOT,
-};
-use netlink_sys::{Protocol, Socket, SocketAddr};
+/// Start of the body of vendor-specific action frames from Nintendo (OUI 00:1f:32).
+const NINTENDO_ACTION_PREFIX: [u8; 4] = [0x7f, 0x00, 0x1f, 0x32];
+
+/// The 2.4 GHz channels 1, 6 and 11 StreetPass peers meet on.
+const STREETPASS_CHANNELS: [u32; 3] = [2412, 2437, 2462];
+
+/// The channel the monitor interface is pinned to for the exchange.
+const EXCHANGE_FREQUENCY: u32 = STREETPASS_CHANNELS[0];
+
+/// SSID of the access point consoles exchange data with.
+const RELAY_SSID: &[u8] = b"cecd";
+
+const BEACON_INTERVAL: u16 = 100;
 
-#[cfg(unix)]
-use std::os::unix::io::{AsRawFd, RawFd};
+const DTIM_PERIOD: u32 = 2;
 
-use std::io::{Error, Result};
+/// How often to transmit a frame before giving up on its receiver.
+const TRANSMIT_ATTEMPTS: usize = 3;
 
-#[derive(Debug)]
-struct PacketSocket(RawFd);
+/// How long to wait for a transmitted frame to be acknowledged before sending it again.
+const ACK_TIMEOUT: Duration = Duration::from_millis(100);
 
-impl PacketSocket {
-    pub fn open() -> Result<Self> {
-        let fd = unsafe { libc::socket(libc::PF_PACKET, libc::SOCK_RAW, libc::ETH_P_ALL.to_be()) };
-        if fd < 0 {
-            Err(Error::last_os_error())
-        } else {
-            Ok(Self(fd))
+/// Set by SIGINT and SIGTERM, to clean up before exiting.
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_shutdown(_signal: libc::c_int) {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+/// Handle SIGINT and SIGTERM by setting [`SHUTDOWN`].
+///
+/// The handlers are installed without `SA_RESTART`, so a blocking receive fails with `EINTR`
+/// and the main loop gets to check the flag.
+fn handle_shutdown_signals() -> Result<()> {
+    for signal in &[libc::SIGINT, libc::SIGTERM] {
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = request_shutdown as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+        if unsafe { libc::sigaction(*signal, &action, std::ptr::null_mut()) } < 0 {
+            return Err(Error::last_os_error());
         }
     }
+
+    Ok(())
 }
 
-impl AsRawFd for PacketSocket {
-    fn as_raw_fd(&self) -> RawFd {
-        self.0
+/// Block SIGINT and SIGTERM on the calling thread, leaving them to interrupt the main thread.
+fn block_shutdown_signals() {
+    unsafe {
+        let mut signals: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut signals);
+        libc::sigaddset(&mut signals, libc::SIGINT);
+        libc::sigaddset(&mut signals, libc::SIGTERM);
+        libc::pthread_sigmask(libc::SIG_BLOCK, &signals, std::ptr::null_mut());
     }
 }
 
-fn main() {
-    let get_family = ControlMessage::GetFamily(FamilyName::new("nl80211"));
-    let mut packet = NetlinkMessage::from(get_family);
-    packet.header.flags = NLM_F_REQUEST;
-    packet.finalize();
+/// Virtual interfaces created for the exchange, removed again on shutdown.
+///
+/// They share the radio with `previous`, the interface they were created next to. Whatever
+/// creating them changed on `previous` is undone after removing them.
+struct VirtualInterfaces {
+    nl80211: Nl80221Family,
+    previous: Interface,
+    /// Changes to `previous`, to undo on removal.
+    changes: InterfaceChanges,
+    monitor: Interface,
+    ap: Interface,
+    /// The BSS of the access point interface, once it is beaconing.
+    bss: Option<BssSettings>,
+}
+
+impl VirtualInterfaces {
+    /// Create a monitor and an access point interface on `wiphy`, the radio of `previous`, bring
+    /// them up and pin the monitor interface to the exchange channel.
+    fn create(
+        socket: &Socket,
+        nl80211: Nl80221Family,
+        wiphy: &Wiphy,
+        previous: Interface,
+    ) -> Option<Self> {
+        let monitor = match nl80211.create_interface(socket, monitor_interface(wiphy)) {
+            Ok(interface) => interface,
+            Err(e) => {
+                eprintln!("failed to create monitor interface: {}", e);
+                return None;
+            }
+        };
+
+        if let Err(e) = set_link(&monitor, true) {
+            eprintln!("failed to bring up monitor interface: {}", e);
+            remove_interface(socket, &nl80211, &monitor);
+            return None;
+        }
+
+        let ap = match nl80211.create_interface(socket, ap_interface(wiphy)) {
+            Ok(interface) => interface,
+            Err(e) => {
+                eprintln!("failed to create access point interface: {}", e);
+                remove_interface(socket, &nl80211, &monitor);
+                return None;
+            }
+        };
+
+        if let Err(e) = set_link(&ap, true) {
+            eprintln!("failed to bring up access point interface: {}", e);
+            remove_interface(socket, &nl80211, &ap);
+            remove_interface(socket, &nl80211, &monitor);
+            return None;
+        }
+
+        let mut interfaces = Self {
+            nl80211,
+            previous,
+            changes: InterfaceChanges::default(),
+            monitor,
+            ap,
+            bss: None,
+        };
+
+        let channel = interfaces
+            .monitor
+            .index
+            .map(|index| SetChannel::no_ht(index, EXCHANGE_FREQUENCY));
+        let pinned = match channel {
+            Some(channel) => interfaces.nl80211.set_channel(socket, channel),
+            None => Err(failure::err_msg("monitor interface has no index")),
+        };
+        if let Err(e) = pinned {
+            eprintln!(
+                "failed to tune monitor interface to {} MHz: {}",
+                EXCHANGE_FREQUENCY, e
+            );
+            interfaces.remove(socket);
+            return None;
+        }
+
+        // A monitor interface already on the radio is retuned along with ours, the only change
+        // to `previous` there is to undo
+        if interfaces.previous.iftype == InterfaceType::Monitor {
+            let channel = SetChannel::current(&interfaces.previous)
+                .filter(|channel| channel.frequency != Frequency::new(EXCHANGE_FREQUENCY));
+            if let Some(channel) = channel {
+                interfaces
+                    .changes
+                    .record(Nl80221Message::SetChannel(channel));
+            }
+        }
+
+        match interfaces.start_ap(socket) {
+            Ok(bss) => interfaces.bss = Some(bss),
+            Err(e) => {
+                eprintln!("failed to start access point: {}", e);
+                interfaces.remove(socket);
+                return None;
+            }
+        }
+
+        if let Err(e) = interfaces.receive_frames() {
+            eprintln!("failed to register for frames: {}", e);
+            interfaces.remove(socket);
+            return None;
+        }
+
+        Some(interfaces)
+    }
+
+    /// Start beaconing on the access point interface, on the exchange channel.
+    fn start_ap(&self, socket: &Socket) -> std::result::Result<BssSettings, failure::Error> {
+        let index = self
+            .ap
+            .index
+            .ok_or_else(|| failure::err_msg("access point interface has no index"))?;
+        let bssid = self
+            .ap
+            .mac
+            .ok_or_else(|| failure::err_msg("access point interface has no MAC address"))?;
+
+        let bss = relay_bss(bssid.octets());
+        self.nl80211
+            .start_ap(socket, start_ap_request(index, &bss))?;
+
+        Ok(bss)
+    }
+
+    /// Register for probe requests and Nintendo action frames on the access point interface.
+    ///
+    /// Probe requests for the relay are answered, action frames are logged.
+    fn receive_frames(&self) -> std::result::Result<(), failure::Error> {
+        let index = self
+            .ap
+            .index
+            .ok_or_else(|| failure::err_msg("access point interface has no index"))?;
+        let bss = self
+            .bss
+            .clone()
+            .ok_or_else(|| failure::err_msg("access point is not running"))?;
+
+        let mut events = self
+            .nl80211
+            .notifications(&[NL80211_MULTICAST_GROUP_MLME])?;
+        for request in frame_registrations(index) {
+            self.nl80211.register_frame(events.socket(), request)?;
+        }
+
+        // Transmit on a separate socket, so replies do not interleave with notifications
+        let socket = Socket::new(Protocol::Generic)?;
+        let nl80211 = self.nl80211.clone();
+
+        std::thread::spawn(move || {
+            block_shutdown_signals();
+
+            loop {
+                let frame = match events.next_event() {
+                    Ok(Nl80221Message::FrameReceived(frame)) => frame,
+                    Ok(Nl80221Message::NewStation(station)) if station.index == Some(index) => {
+                        // The notification leaves out the signal strength
+                        let station = nl80211
+                            .get_station(&socket, index, station.mac)
+                            .unwrap_or(station);
+                        log_station("associated", &station);
+                        continue;
+                    }
+                    Ok(Nl80221Message::StationDeleted(station)) if station.index == Some(index) => {
+                        log_station("left", &station);
+                        continue;
+                    }
+                    Ok(_) => continue,
+                    // Notifications this does not know about
+                    Err(e) if e.downcast_ref::<DecodeError>().is_some() => continue,
+                    Err(e) => {
+                        eprintln!("failed to receive frame: {}", e);
+                        break;
+                    }
+                };
+
+                log_frame(&frame);
+
+                let bytes = match &frame.frame {
+                    Some(bytes) => bytes.as_bytes(),
+                    None => continue,
+                };
+                if ieee80211::frame_type(bytes) != Some(FRAME_TYPE_PROBE_REQUEST) {
+                    continue;
+                }
+
+                let requested = ieee80211::probe_request_ssid(bytes).unwrap_or_default();
+                if let (true, Some(source)) = (bss.matches(requested), ieee80211::source(bytes)) {
+                    let response = bss.probe_response(source);
+                    match transmit(&socket, &nl80211, &mut events, index, response) {
+                        Ok(true) => {}
+                        Ok(false) => eprintln!("probe response was not acknowledged"),
+                        Err(e) => eprintln!("failed to send probe response: {}", e),
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn remove(self, socket: &Socket) {
+        if let (Some(_), Some(index)) = (&self.bss, self.ap.index) {
+            // Deauthenticate these, rather than leave them to notice the access point is gone
+            match self.nl80211.dump_stations(socket, index) {
+                Ok(stations) => {
+                    for station in stations {
+                        log_station("still associated", &station);
+                        let request = DelStation {
+                            index,
+                            mac: Some(station.mac),
+                            reason: Some(ReasonCode::new(ieee80211::REASON_DEAUTH_LEAVING)),
+                        };
+                        if let Err(e) = self.nl80211.del_station(socket, request) {
+                            eprintln!("failed to deauthenticate station: {}", e);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("failed to list stations: {}", e),
+            }
+
+            if let Err(e) = self.nl80211.stop_ap(socket, index) {
+                eprintln!("failed to stop access point: {}", e);
+            }
+        }
+
+        for interface in &[&self.ap, &self.monitor] {
+            if let Err(e) = set_link(interface, false) {
+                eprintln!("failed to bring down interface: {}", e);
+            }
+        }
+
+        remove_interface(socket, &self.nl80211, &self.ap);
+        remove_interface(socket, &self.nl80211, &self.monitor);
+
+        if let Err(e) = self.nl80211.restore_interface(socket, &self.changes) {
+            let name = self
+                .previous
+                .name
+                .as_ref()
+                .map_or("?", InterfaceName::as_str);
+            eprintln!("failed to restore interface {}: {}", name, e);
+        }
+    }
+}
+
+/// Transmit `frame` on interface `index` until it is acknowledged, at most
+/// [`TRANSMIT_ATTEMPTS`] times, and return whether it was.
+fn transmit(
+    socket: &Socket,
+    nl80211: &Nl80221Family,
+    events: &mut Notifications<Nl80221Family>,
+    index: InterfaceIndex,
+    frame: Vec<u8>,
+) -> std::result::Result<bool, failure::Error> {
+    for _ in 0..TRANSMIT_ATTEMPTS {
+        let request = frame_request(index, frame.clone());
+        if let Some(cookie) = nl80211.transmit_frame(socket, request)? {
+            if events.wait_for_ack(cookie, ACK_TIMEOUT)? {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+fn monitor_interface(wiphy: &Wiphy) -> NewInterface {
+    NewInterface {
+        wiphy: wiphy.index,
+        name: InterfaceName::new(format!("{}mon", wiphy.name.as_str())),
+        iftype: InterfaceType::Monitor,
+        mac: None,
+        flags: Some(MonitorFlags::new(vec![
+            MonitorFlag::OtherBss,
+            MonitorFlag::Control,
+        ])),
+    }
+}
+
+fn ap_interface(wiphy: &Wiphy) -> NewInterface {
+    NewInterface {
+        wiphy: wiphy.index,
+        name: InterfaceName::new(format!("{}ap", wiphy.name.as_str())),
+        iftype: InterfaceType::Ap,
+        mac: None,
+        flags: None,
+    }
+}
+
+/// The BSS of the relay, with the address of the access point interface as `bssid`.
+fn relay_bss(bssid: [u8; 6]) -> BssSettings {
+    BssSettings {
+        bssid,
+        ssid: RELAY_SSID.to_vec(),
+        beacon_interval: BEACON_INTERVAL,
+        frequency: EXCHANGE_FREQUENCY,
+    }
+}
+
+fn start_ap_request(index: InterfaceIndex, bss: &BssSettings) -> StartAp {
+    StartAp {
+        index,
+        beacon_interval: ApBeaconInterval::new(BEACON_INTERVAL.into()),
+        dtim_period: DtimPeriod::new(DTIM_PERIOD),
+        beacon_head: BeaconHead::new(bss.beacon_head()),
+        beacon_tail: None,
+        ssid: Ssid::new(bss.ssid.clone()),
+        hidden_ssid: Some(HiddenSsid::NotInUse),
+        auth_type: Some(AuthType::OpenSystem),
+        frequency: Some(Frequency::new(EXCHANGE_FREQUENCY)),
+        channel_type: Some(ChannelType::NoHt),
+        channel_width: None,
+        center_frequency1: None,
+        probe_response: Some(ProbeResponse::new(bss.probe_response(ieee80211::BROADCAST))),
+    }
+}
+
+/// Registrations for probe requests and Nintendo action frames on interface `index`.
+fn frame_registrations(index: InterfaceIndex) -> Vec<RegisterFrame> {
+    [
+        (FRAME_TYPE_PROBE_REQUEST, &[][..]),
+        (FRAME_TYPE_ACTION, &NINTENDO_ACTION_PREFIX[..]),
+    ]
+    .iter()
+    .map(|(frame_type, prefix)| RegisterFrame {
+        index,
+        frame_type: FrameType::new(*frame_type),
+        match_bytes: FrameMatch::new(prefix.to_vec()),
+    })
+    .collect()
+}
+
+fn frame_request(index: InterfaceIndex, frame: Vec<u8>) -> Frame {
+    Frame {
+        index,
+        frequency: None,
+        wait: None,
+        no_cck: None,
+        dont_wait_for_ack: None,
+        frame: RawFrame::new(frame),
+    }
+}
+
+fn scan_request(index: InterfaceIndex) -> TriggerScan {
+    TriggerScan {
+        index,
+        ssids: Some(ScanSsids::wildcard()),
+        frequencies: Some(ScanFrequencies::new(STREETPASS_CHANNELS.iter().copied())),
+        extra_ies: None,
+    }
+}
+
+fn log_frame(frame: &FrameInfo) {
+    let length = frame
+        .frame
+        .as_ref()
+        .map_or(0, |frame| frame.as_bytes().len());
+    let frequency = frame
+        .mhz()
+        .map_or_else(|| "?".to_owned(), |mhz| format!("{} MHz", mhz));
+    let signal = frame
+        .dbm()
+        .map_or_else(|| "?".to_owned(), |dbm| format!("{} dBm", dbm));
+
+    println!(
+        "received {} byte frame on {} at {}",
+        length, frequency, signal
+    );
+}
+
+fn log_station(event: &str, station: &StationInfo) {
+    let mut line = format!("{} {}", format_mac(station.mac()), event);
 
-    let mut buf = vec![0; packet.header.length as usize];
-    packet.serialize(&mut buf);
+    if let Some(stats) = station.stats() {
+        if let Some(secs) = stats.connected_secs() {
+            line += &format!(", connected for {} s", secs);
+        }
+        if let (Some(rx), Some(tx)) = (stats.rx_bytes(), stats.tx_bytes()) {
+            line += &format!(", {} bytes received, {} bytes sent", rx, tx);
+        }
+        if let (Some(rx), Some(tx)) = (stats.rx_packets(), stats.tx_packets()) {
+            line += &format!(" in {} and {} packets", rx, tx);
+        }
+        if let Some(dbm) = stats.dbm() {
+            line += &format!(", at {} dBm", dbm);
+        }
+    }
 
-    println!("packet: {:02x?}", packet);
-    println!("buffer: {:02x?}", buf);
+    println!("{}", line);
+}
 
-    let kernel_unicast = SocketAddr::new(0, 0);
+fn format_mac(mac: [u8; 6]) -> String {
+    format!(
+        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+    )
+}
+
+/// Bring `interface` up or down.
+fn set_link(interface: &Interface, up: bool) -> Result<()> {
+    interface
+        .index
+        .ok_or_else(|| Error::new(std::io::ErrorKind::NotFound, "interface has no index"))?
+        .set_up(up)
+}
+
+fn remove_interface(socket: &Socket, nl80211: &Nl80221Family, interface: &Interface) {
+    let index = match interface.index {
+        Some(index) => index,
+        None => {
+            eprintln!("cannot remove an interface without an index");
+            return;
+        }
+    };
+
+    if let Err(e) = nl80211.del_interface(socket, index) {
+        // The interface may have been renamed since it was created
+        let name = index.name().ok().or_else(|| interface.name.clone());
+        let name = name.as_ref().map_or("?", InterfaceName::as_str);
+        eprintln!("failed to remove interface {}: {}", name, e);
+    }
+}
+
+fn main() {
     let socket = Socket::new(Protocol::Generic).unwrap();
+    handle_shutdown_signals().unwrap();
 
-    socket
-        .send_to(&buf[..packet.buffer_len()], &kernel_unicast, 0)
-        .unwrap();
-
-    let mut recv_buf = vec![0; 4096];
-
-    loop {
-        let (n, _addr) = socket.recv_from(&mut recv_buf, 0).unwrap();
-        match n {
-            0 => break,
-            _ => {
-                let response_buf = &recv_buf[..n];
-                let response = NetlinkMessage::<ControlMessage>::deserialize(response_buf);
-                println!("received: {:02x?}", response_buf);
-                println!("response: {:#02x?}", response);
-
-                if let Ok(NetlinkMessage {
-                    payload: NetlinkPayload::InnerMessage(ControlMessage::NewFamily(new_family)),
-                    ..
-                }) = response
+    let argument = std::env::args().nth(1);
+    if argument.as_deref() == Some("--list-families") {
+        std::process::exit(list_families(&socket));
+    }
+
+    // Without an interface name, use the first wireless interface found
+    let name = argument.map(InterfaceName::new);
+
+    // Follow nl80211 being unloaded and reloaded, e.g. on a driver reset. Listen before
+    // resolving it, so a reload in between is not missed.
+    let watcher = match ControlFamily.notifications(&socket) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("failed to watch for nl80211 reloads: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut registry = FamilyRegistry::new();
+    let mut interfaces = match Nl80221Family::new(&socket, &mut registry) {
+        Ok(nl80211) => match setup(&socket, nl80211, name.as_ref()) {
+            Ok(interfaces) => interfaces,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        Err(e) => {
+            eprintln!("failed to resolve nl80211: {}", e);
+            None
+        }
+    };
+
+    let mut status = 0;
+    for event in watcher {
+        let events = match event {
+            // Already known or stale, e.g. from before nl80211 was resolved above
+            Ok(ControlMessage::NewFamily(family)) if is_known(&registry, &family) => continue,
+            Ok(ControlMessage::DelFamily(family)) if !is_known(&registry, &family) => continue,
+            Ok(event) => {
+                registry.handle_event(&event);
+                vec![event]
+            }
+            Err(_) if SHUTDOWN.load(Ordering::SeqCst) => break,
+            // The socket overflowed, list the families to find out what was missed
+            Err(e) if is_overrun(&e) => match ControlMessage::dump_families(&socket) {
+                Ok(families) => registry.replace_all(families),
+                Err(e) => {
+                    eprintln!("failed to list families: {}", e);
+                    status = 1;
+                    break;
+                }
+            },
+            Err(e) => {
+                eprintln!("failed to receive event: {}", e);
+                status = 1;
+                break;
+            }
+        };
+
+        for event in events {
+            match event {
+                ControlMessage::NewFamily(family)
+                    if family.name.as_str() == NL80211_FAMILY_NAME =>
+                {
+                    println!("nl80211 registered with id {:#06x}", u16::from(family.id));
+                    match Nl80221Family::new(&socket, &mut registry) {
+                        // The radio was usable before, keep running in case it is again
+                        Ok(nl80211) => match setup(&socket, nl80211, name.as_ref()) {
+                            Ok(new) => interfaces = new,
+                            Err(e) => eprintln!("{}", e),
+                        },
+                        Err(e) => eprintln!("failed to resolve nl80211: {}", e),
+                    }
+                }
+                ControlMessage::DelFamily(family)
+                    if family.name.as_str() == NL80211_FAMILY_NAME =>
                 {
-                    get_iface(&socket, Nl80221Family::new(new_family))
+                    // The interfaces went away with the family
+                    println!("nl80211 unregistered");
+                    interfaces = None;
                 }
+                // Other families and multicast groups only concern the registry
+                _ => {}
             }
         }
     }
+
+    if let Some(interfaces) = interfaces {
+        interfaces.remove(&socket);
+    }
+    std::process::exit(status);
 }
 
-fn get_iface(socket: &Socket, nl80211: Nl80221Family) {
-    let get_iface = nl80211.tag_message(Nl80221Message::GetInterface(13.into()));
-    let mut packet = NetlinkMessage::from(get_iface);
-    packet.header.flags = NLM_F_REQUEST;
-    packet.finalize();
+/// Print the id and name of every generic netlink family, and return the exit status.
+fn list_families(socket: &Socket) -> i32 {
+    match ControlMessage::dump_families(socket) {
+        Ok(families) => {
+            for family in families {
+                println!("{:#06x} {}", u16::from(family.id), family.name.as_str());
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("failed to list families: {}", e);
+            1
+        }
+    }
+}
+
+/// Whether `family` is registered under the same id in `registry`.
+fn is_known(registry: &FamilyRegistry, family: &NewFamily) -> bool {
+    registry.by_name(&family.name).map(|known| &known.id) == Some(&family.id)
+}
+
+/// Whether receiving failed because the socket buffer overflowed and notifications were dropped.
+fn is_overrun(error: &failure::Error) -> bool {
+    error.downcast_ref::<Error>().and_then(Error::raw_os_error) == Some(libc::ENOBUFS)
+}
+
+/// Set up the virtual interfaces next to the interface called `name`.
+///
+/// Fails if its radio or the kernel cannot be used for StreetPass at all. Other failures are
+/// logged and leave the daemon without interfaces, in case they go away with the next reload of
+/// nl80211.
+fn setup(
+    socket: &Socket,
+    nl80211: Nl80221Family,
+    name: Option<&InterfaceName>,
+) -> std::result::Result<Option<VirtualInterfaces>, failure::Error> {
+    let interface = match get_iface(socket, &nl80211, name) {
+        Some(interface) => interface,
+        None => return Ok(None),
+    };
+
+    let wiphy = match nl80211.get_wiphy(socket, interface.wiphy) {
+        Ok(wiphy) => wiphy,
+        Err(e) => {
+            eprintln!("failed to query wiphy capabilities: {}", e);
+            return Ok(None);
+        }
+    };
+
+    let problems = check_wiphy(&wiphy);
+    if !problems.is_empty() {
+        return Err(failure::format_err!(
+            "{} cannot be used for StreetPass: {}",
+            wiphy.name.as_str(),
+            problems.join(", ")
+        ));
+    }
+
+    if let Some(index) = interface.index {
+        check_requests(socket, &nl80211, &wiphy, index)?;
+        scan_nearby(socket, &nl80211, index);
+    }
+
+    Ok(VirtualInterfaces::create(
+        socket, nl80211, &wiphy, interface,
+    ))
+}
+
+/// Actively scan the StreetPass channels for consoles and relays in range, and list them.
+fn scan_nearby(socket: &Socket, nl80211: &Nl80221Family, index: InterfaceIndex) {
+    match nl80211.scan(socket, scan_request(index)) {
+        Ok(networks) => {
+            for bss in networks {
+                let signal = bss
+                    .dbm()
+                    .map_or_else(|| "?".to_owned(), |dbm| format!("{:.0} dBm", dbm));
+                println!("{} {} MHz {}", format_mac(bss.bssid()), bss.mhz(), signal);
+            }
+        }
+        // Not fatal, e.g. when the interface is down
+        Err(e) => eprintln!("failed to scan: {}", e),
+    }
+}
 
-    let mut buf = vec![0; packet.header.length as usize];
-    packet.serialize(&mut buf);
+fn get_iface(
+    socket: &Socket,
+    nl80211: &Nl80221Family,
+    name: Option<&InterfaceName>,
+) -> Option<Interface> {
+    let interface = match name {
+        Some(name) => InterfaceIndex::from_name(name)
+            .or_else(|_| nl80211.resolve_index(socket, name))
+            .and_then(|index| nl80211.get_interface(socket, index))
+            .map(Some),
+        None => nl80211.dump_interfaces(socket).map(|interfaces| {
+            interfaces
+                .into_iter()
+                .find(|interface| interface.index.is_some())
+        }),
+    };
 
-    let send_buf = &buf[..packet.buffer_len()];
-    println!("get_family: {:02x?}", packet);
-    println!("sending get_family: {:02x?}", send_buf);
-    socket.send_to(send_buf, &SocketAddr::new(0, 0), 0).unwrap();
+    match interface {
+        Ok(Some(interface)) => {
+            println!(
+                "using interface {} ({:?}, {})",
+                interface.name.as_ref().map_or("?", InterfaceName::as_str),
+                interface.iftype,
+                interface.mac.map_or_else(
+                    || "no MAC address".to_owned(),
+                    |mac| format_mac(mac.octets())
+                ),
+            );
+            return Some(interface);
+        }
+        Ok(None) => eprintln!("no matching wireless interface found"),
+        Err(e) => eprintln!("failed to list wireless interfaces: {}", e),
+    }
 
-    let mut recv_buf = vec![0; 4096];
+    None
+}
 
-    loop {
-        let (n, _addr) = socket.recv_from(&mut recv_buf, 0).unwrap();
-        match n {
-            0 => break,
-            n => {
-                let response_buf = &recv_buf[..n];
-                println!("received: {:02x?}", response_buf);
+/// Check the requests the daemon sends against the kernel's nl80211 policy, so a kernel that
+/// cannot handle them is noticed before any interface is created.
+///
+/// `index` stands in for the interfaces yet to be created, the checks only look at which
+/// attributes are sent.
+fn check_requests(
+    socket: &Socket,
+    nl80211: &Nl80221Family,
+    wiphy: &Wiphy,
+    index: InterfaceIndex,
+) -> std::result::Result<(), failure::Error> {
+    let policy = match ControlMessage::get_policy(socket, nl80211.name()) {
+        Ok(policy) => policy,
+        Err(e) => {
+            eprintln!(
+                "failed to fetch nl80211 policy, not checking attributes: {}",
+                e
+            );
+            return Ok(());
+        }
+    };
+
+    let bss = relay_bss([0; 6]);
+    let mut requests = vec![
+        Nl80221Message::DumpInterfaces,
+        Nl80221Message::CreateInterface(monitor_interface(wiphy)),
+        Nl80221Message::CreateInterface(ap_interface(wiphy)),
+        Nl80221Message::SetChannel(SetChannel::no_ht(index, EXCHANGE_FREQUENCY)),
+        Nl80221Message::TriggerScan(scan_request(index)),
+        Nl80221Message::GetScan(index),
+        Nl80221Message::StartAp(start_ap_request(index, &bss)),
+        Nl80221Message::Frame(frame_request(
+            index,
+            bss.probe_response(ieee80211::BROADCAST),
+        )),
+        Nl80221Message::DumpStations(index),
+    ];
+    requests.extend(
+        frame_registrations(index)
+            .into_iter()
+            .map(Nl80221Message::RegisterFrame),
+    );
+
+    for request in &requests {
+        request.check_policy(&policy)?;
+    }
+
+    Ok(())
+}
+
+/// List everything missing from `wiphy` for taking part in StreetPass.
+fn check_wiphy(wiphy: &Wiphy) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (iftype, mode) in &[
+        (InterfaceType::Monitor, "monitor"),
+        (InterfaceType::Ap, "access point"),
+    ] {
+        if !wiphy.iftypes.contains(*iftype) {
+            problems.push(format!("{} mode is not supported", mode));
+        }
+    }
+
+    if !wiphy.commands.contains(NL80211_CMD_FRAME)
+        || !wiphy
+            .tx_frame_types
+            .contains(InterfaceType::Ap, FRAME_TYPE_ACTION)
+    {
+        problems.push("action frames cannot be injected in access point mode".to_owned());
+    }
+
+    if !wiphy.commands.contains(NL80211_CMD_REGISTER_FRAME)
+        || !wiphy
+            .rx_frame_types
+            .contains(InterfaceType::Ap, FRAME_TYPE_ACTION)
+    {
+        problems.push("action frames cannot be received in access point mode".to_owned());
+    }
+
+    for (number, mhz) in [1, 6, 11].iter().zip(&STREETPASS_CHANNELS) {
+        match wiphy.channel(*mhz) {
+            None => problems.push(format!("channel {} ({} MHz) is not supported", number, mhz)),
+            Some(channel) if channel.disabled.is_some() => {
+                problems.push(format!("channel {} ({} MHz) is disabled", number, mhz))
             }
+            Some(channel) if channel.no_ir.is_some() => problems.push(format!(
+                "channel {} ({} MHz) does not allow transmitting",
+                number, mhz
+            )),
+            Some(_) => {}
         }
     }
+
+    problems
 }
@@ -1,21 +1,202 @@
-use byteorder::{ByteOrder, NativeEndian};
-use failure::{Compat, ResultExt};
-use netlink_packet_core::{
-    NetlinkDeserializable, NetlinkHeader, NetlinkMessage, NetlinkPayload, NetlinkSerializable,
-};
+use cecd_derive::{GenlMessage, NlaSet};
+use failure::{format_err, Error};
 use netlink_packet_utils::{
     nla::{Nla, NlaBuffer, NlasIterator},
     parsers,
-    traits::{Emitable, Parseable, ParseableParametrized},
+    traits::{Emitable, Parseable},
     DecodeError,
 };
 
-use crate::controller::{FamilyId, NewFamily};
+use netlink_sys::Socket;
+
+use std::{
+    ffi::{CStr, CString},
+    io,
+    time::{Duration, Instant},
+};
+
+use crate::controller::{FamilyId, FamilyName, FamilyRegistry, NewFamily, Policy};
+use crate::genl::{self, AttributeValue, GenlFamily, GenlPayload, Notifications, RawAttribute};
 
+pub const NL80211_FAMILY_NAME: &str = "nl80211";
+
+/// Multicast group for scan requests being started, completed or aborted.
+pub const NL80211_MULTICAST_GROUP_SCAN: &str = "scan";
+
+/// Multicast group for MLME events, such as the status of transmitted frames.
+pub const NL80211_MULTICAST_GROUP_MLME: &str = "mlme";
+
+const NL80211_CMD_GET_WIPHY: u8 = 1;
+const NL80211_CMD_NEW_WIPHY: u8 = 3;
 const NL80211_CMD_GET_INTERFACE: u8 = 5;
+const NL80211_CMD_SET_INTERFACE: u8 = 6;
+const NL80211_CMD_NEW_INTERFACE: u8 = 7;
+const NL80211_CMD_DEL_INTERFACE: u8 = 8;
+const NL80211_CMD_SET_BEACON: u8 = 14;
+const NL80211_CMD_START_AP: u8 = 15;
+const NL80211_CMD_STOP_AP: u8 = 16;
+const NL80211_CMD_GET_STATION: u8 = 17;
+const NL80211_CMD_NEW_STATION: u8 = 19;
+const NL80211_CMD_DEL_STATION: u8 = 20;
+const NL80211_CMD_GET_SCAN: u8 = 32;
+const NL80211_CMD_TRIGGER_SCAN: u8 = 33;
+const NL80211_CMD_NEW_SCAN_RESULTS: u8 = 34;
+const NL80211_CMD_SCAN_ABORTED: u8 = 35;
+const NL80211_CMD_REMAIN_ON_CHANNEL: u8 = 55;
+const NL80211_CMD_CANCEL_REMAIN_ON_CHANNEL: u8 = 56;
+pub const NL80211_CMD_REGISTER_FRAME: u8 = 58;
+pub const NL80211_CMD_FRAME: u8 = 59;
+const NL80211_CMD_FRAME_TX_STATUS: u8 = 60;
+const NL80211_CMD_SET_CHANNEL: u8 = 65;
 
+const NL80211_ATTR_WIPHY: u16 = 1;
+const NL80211_ATTR_WIPHY_NAME: u16 = 2;
 const NL80211_ATTR_IFINDEX: u16 = 3;
 const NL80211_ATTR_IFNAME: u16 = 4;
+const NL80211_ATTR_IFTYPE: u16 = 5;
+const NL80211_ATTR_MAC: u16 = 6;
+const NL80211_ATTR_BEACON_INTERVAL: u16 = 12;
+const NL80211_ATTR_DTIM_PERIOD: u16 = 13;
+const NL80211_ATTR_BEACON_HEAD: u16 = 14;
+const NL80211_ATTR_BEACON_TAIL: u16 = 15;
+const NL80211_ATTR_STA_INFO: u16 = 21;
+const NL80211_ATTR_WIPHY_BANDS: u16 = 22;
+const NL80211_ATTR_MNTR_FLAGS: u16 = 23;
+const NL80211_ATTR_SUPPORTED_IFTYPES: u16 = 32;
+const NL80211_ATTR_WIPHY_FREQ: u16 = 38;
+const NL80211_ATTR_WIPHY_CHANNEL_TYPE: u16 = 39;
+const NL80211_ATTR_IE: u16 = 42;
+const NL80211_ATTR_MAX_NUM_SCAN_SSIDS: u16 = 43;
+const NL80211_ATTR_SCAN_FREQUENCIES: u16 = 44;
+const NL80211_ATTR_SCAN_SSIDS: u16 = 45;
+const NL80211_ATTR_GENERATION: u16 = 46;
+const NL80211_ATTR_BSS: u16 = 47;
+const NL80211_ATTR_SUPPORTED_COMMANDS: u16 = 50;
+const NL80211_ATTR_FRAME: u16 = 51;
+const NL80211_ATTR_SSID: u16 = 52;
+const NL80211_ATTR_AUTH_TYPE: u16 = 53;
+const NL80211_ATTR_REASON_CODE: u16 = 54;
+const NL80211_ATTR_CIPHER_SUITES: u16 = 57;
+const NL80211_ATTR_DURATION: u16 = 87;
+const NL80211_ATTR_COOKIE: u16 = 88;
+const NL80211_ATTR_FRAME_MATCH: u16 = 91;
+const NL80211_ATTR_ACK: u16 = 92;
+const NL80211_ATTR_TX_FRAME_TYPES: u16 = 99;
+const NL80211_ATTR_RX_FRAME_TYPES: u16 = 100;
+const NL80211_ATTR_FRAME_TYPE: u16 = 101;
+const NL80211_ATTR_INTERFACE_COMBINATIONS: u16 = 120;
+const NL80211_ATTR_HIDDEN_SSID: u16 = 126;
+const NL80211_ATTR_TX_NO_CCK_RATE: u16 = 135;
+const NL80211_ATTR_DONT_WAIT_FOR_ACK: u16 = 142;
+const NL80211_ATTR_PROBE_RESP: u16 = 145;
+const NL80211_ATTR_RX_SIGNAL_DBM: u16 = 151;
+const NL80211_ATTR_WDEV: u16 = 153;
+const NL80211_ATTR_CHANNEL_WIDTH: u16 = 159;
+const NL80211_ATTR_CENTER_FREQ1: u16 = 160;
+const NL80211_ATTR_CENTER_FREQ2: u16 = 161;
+const NL80211_ATTR_SPLIT_WIPHY_DUMP: u16 = 174;
+
+const NL80211_BAND_ATTR_FREQS: u16 = 1;
+
+const NL80211_FREQUENCY_ATTR_FREQ: u16 = 1;
+const NL80211_FREQUENCY_ATTR_DISABLED: u16 = 2;
+const NL80211_FREQUENCY_ATTR_NO_IR: u16 = 3;
+const NL80211_FREQUENCY_ATTR_RADAR: u16 = 5;
+const NL80211_FREQUENCY_ATTR_MAX_TX_POWER: u16 = 6;
+
+const NL80211_IFACE_COMB_LIMITS: u16 = 1;
+const NL80211_IFACE_COMB_MAXNUM: u16 = 2;
+const NL80211_IFACE_COMB_STA_AP_BI_MATCH: u16 = 3;
+const NL80211_IFACE_COMB_NUM_CHANNELS: u16 = 4;
+const NL80211_IFACE_COMB_RADAR_DETECT_WIDTHS: u16 = 5;
+const NL80211_IFACE_COMB_RADAR_DETECT_REGIONS: u16 = 6;
+
+const NL80211_IFACE_LIMIT_MAX: u16 = 1;
+const NL80211_IFACE_LIMIT_TYPES: u16 = 2;
+
+const NL80211_BSS_BSSID: u16 = 1;
+const NL80211_BSS_FREQUENCY: u16 = 2;
+const NL80211_BSS_TSF: u16 = 3;
+const NL80211_BSS_BEACON_INTERVAL: u16 = 4;
+const NL80211_BSS_CAPABILITY: u16 = 5;
+const NL80211_BSS_INFORMATION_ELEMENTS: u16 = 6;
+const NL80211_BSS_SIGNAL_MBM: u16 = 7;
+const NL80211_BSS_SIGNAL_UNSPEC: u16 = 8;
+const NL80211_BSS_SEEN_MS_AGO: u16 = 10;
+const NL80211_BSS_BEACON_IES: u16 = 11;
+
+const NL80211_STA_INFO_INACTIVE_TIME: u16 = 1;
+const NL80211_STA_INFO_RX_BYTES: u16 = 2;
+const NL80211_STA_INFO_TX_BYTES: u16 = 3;
+const NL80211_STA_INFO_SIGNAL: u16 = 7;
+const NL80211_STA_INFO_RX_PACKETS: u16 = 9;
+const NL80211_STA_INFO_TX_PACKETS: u16 = 10;
+const NL80211_STA_INFO_TX_RETRIES: u16 = 11;
+const NL80211_STA_INFO_TX_FAILED: u16 = 12;
+const NL80211_STA_INFO_SIGNAL_AVG: u16 = 13;
+const NL80211_STA_INFO_CONNECTED_TIME: u16 = 16;
+const NL80211_STA_INFO_RX_BYTES64: u16 = 23;
+const NL80211_STA_INFO_TX_BYTES64: u16 = 24;
+const NL80211_STA_INFO_ASSOC_AT_BOOTTIME: u16 = 42;
+
+const NL80211_MNTR_FLAG_FCSFAIL: u16 = 1;
+const NL80211_MNTR_FLAG_PLCPFAIL: u16 = 2;
+const NL80211_MNTR_FLAG_CONTROL: u16 = 3;
+const NL80211_MNTR_FLAG_OTHER_BSS: u16 = 4;
+const NL80211_MNTR_FLAG_COOK_FRAMES: u16 = 5;
+const NL80211_MNTR_FLAG_ACTIVE: u16 = 6;
+
+const NL80211_IFTYPE_UNSPECIFIED: u32 = 0;
+const NL80211_IFTYPE_ADHOC: u32 = 1;
+const NL80211_IFTYPE_STATION: u32 = 2;
+const NL80211_IFTYPE_AP: u32 = 3;
+const NL80211_IFTYPE_AP_VLAN: u32 = 4;
+const NL80211_IFTYPE_WDS: u32 = 5;
+const NL80211_IFTYPE_MONITOR: u32 = 6;
+const NL80211_IFTYPE_MESH_POINT: u32 = 7;
+const NL80211_IFTYPE_P2P_CLIENT: u32 = 8;
+const NL80211_IFTYPE_P2P_GO: u32 = 9;
+const NL80211_IFTYPE_P2P_DEVICE: u32 = 10;
+const NL80211_IFTYPE_OCB: u32 = 11;
+const NL80211_IFTYPE_NAN: u32 = 12;
+
+const NL80211_BAND_2GHZ: u32 = 0;
+const NL80211_BAND_5GHZ: u32 = 1;
+const NL80211_BAND_60GHZ: u32 = 2;
+const NL80211_BAND_6GHZ: u32 = 3;
+const NL80211_BAND_S1GHZ: u32 = 4;
+const NL80211_BAND_LC: u32 = 5;
+
+const NL80211_HIDDEN_SSID_NOT_IN_USE: u32 = 0;
+const NL80211_HIDDEN_SSID_ZERO_LEN: u32 = 1;
+const NL80211_HIDDEN_SSID_ZERO_CONTENTS: u32 = 2;
+
+const NL80211_AUTHTYPE_OPEN_SYSTEM: u32 = 0;
+const NL80211_AUTHTYPE_SHARED_KEY: u32 = 1;
+const NL80211_AUTHTYPE_FT: u32 = 2;
+const NL80211_AUTHTYPE_NETWORK_EAP: u32 = 3;
+const NL80211_AUTHTYPE_SAE: u32 = 4;
+const NL80211_AUTHTYPE_AUTOMATIC: u32 = 8;
+
+const NL80211_CHAN_NO_HT: u32 = 0;
+const NL80211_CHAN_HT20: u32 = 1;
+const NL80211_CHAN_HT40MINUS: u32 = 2;
+const NL80211_CHAN_HT40PLUS: u32 = 3;
+
+const NL80211_CHAN_WIDTH_20_NOHT: u32 = 0;
+const NL80211_CHAN_WIDTH_20: u32 = 1;
+const NL80211_CHAN_WIDTH_40: u32 = 2;
+const NL80211_CHAN_WIDTH_80: u32 = 3;
+const NL80211_CHAN_WIDTH_80P80: u32 = 4;
+const NL80211_CHAN_WIDTH_160: u32 = 5;
+const NL80211_CHAN_WIDTH_5: u32 = 6;
+const NL80211_CHAN_WIDTH_10: u32 = 7;
+const NL80211_CHAN_WIDTH_1: u32 = 8;
+const NL80211_CHAN_WIDTH_2: u32 = 9;
+const NL80211_CHAN_WIDTH_4: u32 = 10;
+const NL80211_CHAN_WIDTH_8: u32 = 11;
+const NL80211_CHAN_WIDTH_16: u32 = 12;
+const NL80211_CHAN_WIDTH_320: u32 = 13;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(transparent)]
@@ -24,98 +205,2924 @@ pub struct InterfaceIndex(u32);
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InterfaceName(String);
 
+/// Index of a physical radio.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WiphyIndex(u32);
+
+/// Identifier of a wireless device, which unlike an interface index also covers devices
+/// without a netdev such as P2P devices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WirelessDevice(u64);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MacAddress([u8; 6]);
+
+/// Counter bumped whenever the interface changes, to detect dumps spanning a change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Generation(u32);
+
+/// Frequency of the control channel in MHz.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Frequency(u32);
+
+/// Center frequency of the first segment of the channel in MHz.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CenterFrequency1(u32);
+
+/// Center frequency of the second segment of an 80+80 MHz channel in MHz.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CenterFrequency2(u32);
+
+kernel_enum! {
+    pub enum InterfaceType: u32 {
+        Unspecified = NL80211_IFTYPE_UNSPECIFIED,
+        Adhoc = NL80211_IFTYPE_ADHOC,
+        Station = NL80211_IFTYPE_STATION,
+        Ap = NL80211_IFTYPE_AP,
+        ApVlan = NL80211_IFTYPE_AP_VLAN,
+        Wds = NL80211_IFTYPE_WDS,
+        Monitor = NL80211_IFTYPE_MONITOR,
+        MeshPoint = NL80211_IFTYPE_MESH_POINT,
+        P2pClient = NL80211_IFTYPE_P2P_CLIENT,
+        P2pGo = NL80211_IFTYPE_P2P_GO,
+        P2pDevice = NL80211_IFTYPE_P2P_DEVICE,
+        Ocb = NL80211_IFTYPE_OCB,
+        Nan = NL80211_IFTYPE_NAN,
+    }
+}
+
+kernel_enum! {
+    /// Legacy description of a 20 or 40 MHz channel, superseded by [`ChannelWidth`].
+    pub enum ChannelType: u32 {
+        NoHt = NL80211_CHAN_NO_HT,
+        Ht20 = NL80211_CHAN_HT20,
+        Ht40Minus = NL80211_CHAN_HT40MINUS,
+        Ht40Plus = NL80211_CHAN_HT40PLUS,
+    }
+}
+
+kernel_enum! {
+    pub enum ChannelWidth: u32 {
+        Width20NoHt = NL80211_CHAN_WIDTH_20_NOHT,
+        Width20 = NL80211_CHAN_WIDTH_20,
+        Width40 = NL80211_CHAN_WIDTH_40,
+        Width80 = NL80211_CHAN_WIDTH_80,
+        Width80P80 = NL80211_CHAN_WIDTH_80P80,
+        Width160 = NL80211_CHAN_WIDTH_160,
+        Width5 = NL80211_CHAN_WIDTH_5,
+        Width10 = NL80211_CHAN_WIDTH_10,
+        Width1 = NL80211_CHAN_WIDTH_1,
+        Width2 = NL80211_CHAN_WIDTH_2,
+        Width4 = NL80211_CHAN_WIDTH_4,
+        Width8 = NL80211_CHAN_WIDTH_8,
+        Width16 = NL80211_CHAN_WIDTH_16,
+        Width320 = NL80211_CHAN_WIDTH_320,
+    }
+}
+
 impl_wrapped_attribute!(InterfaceIndex(u32): NL80211_ATTR_IFINDEX);
 impl_wrapped_attribute!(InterfaceName(String): NL80211_ATTR_IFNAME);
+impl_wrapped_attribute!(WiphyIndex(u32): NL80211_ATTR_WIPHY);
+impl_wrapped_attribute!(WirelessDevice(u64): NL80211_ATTR_WDEV);
+impl_wrapped_attribute!(MacAddress([u8; 6]): NL80211_ATTR_MAC);
+impl_wrapped_attribute!(Generation(u32): NL80211_ATTR_GENERATION);
+impl_wrapped_attribute!(Frequency(u32): NL80211_ATTR_WIPHY_FREQ);
+impl_wrapped_attribute!(CenterFrequency1(u32): NL80211_ATTR_CENTER_FREQ1);
+impl_wrapped_attribute!(CenterFrequency2(u32): NL80211_ATTR_CENTER_FREQ2);
+impl_wrapped_attribute!(InterfaceType as u32: NL80211_ATTR_IFTYPE);
+impl_wrapped_attribute!(ChannelType as u32: NL80211_ATTR_WIPHY_CHANNEL_TYPE);
+impl_wrapped_attribute!(ChannelWidth as u32: NL80211_ATTR_CHANNEL_WIDTH);
 
-impl InterfaceIndex {
-    fn from_name(name: &str) -> std::io::Result<Self> {
-        extern "C" {
-            fn if_nametoindex(ifname: *const libc::c_char) -> libc::c_uint;
-        }
+/// A wireless interface, as reported by `NL80211_CMD_GET_INTERFACE`.
+///
+/// Devices without a netdev, such as P2P devices, have neither an index nor a name. The channel
+/// is only known while the interface is operating on one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    pub index: Option<InterfaceIndex>,
+    pub name: Option<InterfaceName>,
+    pub wiphy: WiphyIndex,
+    pub iftype: InterfaceType,
+    pub wdev: Option<WirelessDevice>,
+    pub mac: Option<MacAddress>,
+    pub generation: Option<Generation>,
+    pub frequency: Option<Frequency>,
+    pub channel_width: Option<ChannelWidth>,
+    pub channel_type: Option<ChannelType>,
+    pub center_frequency1: Option<CenterFrequency1>,
+    pub center_frequency2: Option<CenterFrequency2>,
+}
+
+// Same order as the kernel's nl80211_send_iface()
+impl_nested_attribute! {
+    Interface, unknown => skip:
+        NL80211_ATTR_IFINDEX => index: Option<InterfaceIndex>,
+        NL80211_ATTR_IFNAME => name: Option<InterfaceName>,
+        NL80211_ATTR_WIPHY => wiphy: WiphyIndex,
+        NL80211_ATTR_IFTYPE => iftype: InterfaceType,
+        NL80211_ATTR_WDEV => wdev: Option<WirelessDevice>,
+        NL80211_ATTR_MAC => mac: Option<MacAddress>,
+        NL80211_ATTR_GENERATION => generation: Option<Generation>,
+        NL80211_ATTR_WIPHY_FREQ => frequency: Option<Frequency>,
+        NL80211_ATTR_CHANNEL_WIDTH => channel_width: Option<ChannelWidth>,
+        NL80211_ATTR_WIPHY_CHANNEL_TYPE => channel_type: Option<ChannelType>,
+        NL80211_ATTR_CENTER_FREQ1 => center_frequency1: Option<CenterFrequency1>,
+        NL80211_ATTR_CENTER_FREQ2 => center_frequency2: Option<CenterFrequency2>,
+}
 
-        let res = unsafe { if_nametoindex(name.as_ptr() as *const libc::c_char) };
+/// Frames a monitor interface passes up in addition to the ones it would receive anyway.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, NlaSet)]
+pub enum MonitorFlag {
+    /// Frames failing the FCS check.
+    #[nla(kind = NL80211_MNTR_FLAG_FCSFAIL)]
+    FcsFail,
+    /// Frames failing the PLCP CRC.
+    #[nla(kind = NL80211_MNTR_FLAG_PLCPFAIL)]
+    PlcpFail,
+    /// Control frames.
+    #[nla(kind = NL80211_MNTR_FLAG_CONTROL)]
+    Control,
+    /// Frames addressed to other BSSes.
+    #[nla(kind = NL80211_MNTR_FLAG_OTHER_BSS)]
+    OtherBss,
+    /// Frames as processed by the stack rather than raw.
+    #[nla(kind = NL80211_MNTR_FLAG_COOK_FRAMES)]
+    CookFrames,
+    /// Acknowledge frames addressed to the interface's MAC address.
+    #[nla(kind = NL80211_MNTR_FLAG_ACTIVE)]
+    Active,
+}
 
-        match res {
-            0 => Err(std::io::Error::last_os_error()),
-            index => Ok(Self(index)),
-        }
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MonitorFlags(Vec<MonitorFlag>);
+
+impl AttributeValue for Vec<MonitorFlag> {
+    const NESTED: bool = true;
+
+    fn value_len(&self) -> usize {
+        self.as_slice().buffer_len()
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        self.as_slice().emit(buffer)
+    }
+
+    fn parse_value(value: &[u8]) -> Result<Self, DecodeError> {
+        NlasIterator::new(value)
+            .map(|attribute| attribute.and_then(|attribute| MonitorFlag::parse(&attribute)))
+            .collect()
+    }
+}
+
+impl_wrapped_attribute!(MonitorFlags(Vec<MonitorFlag>): NL80211_ATTR_MNTR_FLAGS);
+
+impl MonitorFlags {
+    pub fn new<I: IntoIterator<Item = MonitorFlag>>(flags: I) -> Self {
+        Self(flags.into_iter().collect())
     }
 }
 
+/// Request to create a virtual interface on a radio with `NL80211_CMD_NEW_INTERFACE`.
+///
+/// Monitor flags only apply to monitor interfaces. Without a MAC address, the interface gets
+/// the radio's permanent address.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Nl80221Message {
-    GetInterface(InterfaceIndex),
+pub struct NewInterface {
+    pub wiphy: WiphyIndex,
+    pub name: InterfaceName,
+    pub iftype: InterfaceType,
+    pub mac: Option<MacAddress>,
+    pub flags: Option<MonitorFlags>,
 }
 
-#[derive(Debug)]
-pub struct Nl80221Family {
-    pub family: NewFamily,
+impl_nested_attribute! {
+    NewInterface:
+        NL80211_ATTR_WIPHY => wiphy: WiphyIndex,
+        NL80211_ATTR_IFNAME => name: InterfaceName,
+        NL80211_ATTR_IFTYPE => iftype: InterfaceType,
+        NL80211_ATTR_MAC => mac: Option<MacAddress>,
+        NL80211_ATTR_MNTR_FLAGS => flags: Option<MonitorFlags>,
 }
 
+/// Request to change the type of an interface with `NL80211_CMD_SET_INTERFACE`.
+///
+/// Most drivers only allow this while the interface is down.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Nl80221TaggedMessage {
-    family_id: FamilyId,
-    pub message: Nl80221Message,
+pub struct SetInterface {
+    pub index: InterfaceIndex,
+    pub iftype: InterfaceType,
 }
 
-impl Nl80221Family {
-    pub fn new(family: NewFamily) -> Self {
-        Self { family }
-    }
+impl_nested_attribute! {
+    SetInterface:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_IFTYPE => iftype: InterfaceType,
+}
+
+/// Request to tune an interface with `NL80211_CMD_SET_CHANNEL`.
+///
+/// The channel is either given by a legacy channel type, or by a width and center frequency.
+/// Only monitor interfaces and interfaces not yet operating can be tuned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetChannel {
+    pub index: InterfaceIndex,
+    pub frequency: Frequency,
+    pub channel_type: Option<ChannelType>,
+    pub channel_width: Option<ChannelWidth>,
+    pub center_frequency1: Option<CenterFrequency1>,
+}
 
-    pub fn tag_message(&self, message: Nl80221Message) -> Nl80221TaggedMessage {
-        Nl80221TaggedMessage {
-            message,
-            family_id: self.family.id.clone(),
+impl_nested_attribute! {
+    SetChannel:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_WIPHY_FREQ => frequency: Frequency,
+        NL80211_ATTR_WIPHY_CHANNEL_TYPE => channel_type: Option<ChannelType>,
+        NL80211_ATTR_CHANNEL_WIDTH => channel_width: Option<ChannelWidth>,
+        NL80211_ATTR_CENTER_FREQ1 => center_frequency1: Option<CenterFrequency1>,
+}
+
+impl SetChannel {
+    /// Tune to the 20 MHz channel at `mhz`, without HT.
+    pub fn no_ht(index: InterfaceIndex, mhz: u32) -> Self {
+        Self {
+            index,
+            frequency: Frequency(mhz),
+            channel_type: Some(ChannelType::NoHt),
+            channel_width: None,
+            center_frequency1: None,
         }
     }
+
+    /// Tune back to the channel `interface` currently operates on, if any.
+    pub fn current(interface: &Interface) -> Option<Self> {
+        let index = interface.index?;
+        let frequency = interface.frequency?;
+
+        // The width describes the channel more precisely than the legacy type
+        let (channel_type, channel_width, center_frequency1) =
+            match (interface.channel_width, interface.center_frequency1) {
+                (Some(width), Some(center)) => (None, Some(width), Some(center)),
+                _ => (interface.channel_type, None, None),
+            };
+
+        Some(Self {
+            index,
+            frequency,
+            channel_type,
+            channel_width,
+            center_frequency1,
+        })
+    }
 }
 
-impl Nl80221Message {
-    fn attribute_size(&self) -> usize {
-        match self {
-            Self::GetInterface(index) => index.buffer_len(),
+/// What was changed on an existing interface, recorded as the requests that undo it, so that
+/// exactly that can be undone.
+///
+/// Most interface types reject `NL80211_CMD_SET_CHANNEL`, so only a recorded channel is tuned
+/// back to, and likewise for the type.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InterfaceChanges {
+    undo: Vec<Nl80221Message>,
+}
+
+impl InterfaceChanges {
+    /// Record a change, with `undo` being the request that reverts it.
+    pub fn record(&mut self, undo: Nl80221Message) {
+        self.undo.push(undo)
+    }
+
+    /// The requests undoing the recorded changes, the latest change first.
+    pub fn undo_requests(&self) -> impl Iterator<Item = &Nl80221Message> {
+        self.undo.iter().rev()
+    }
+}
+
+/// SSIDs to send probe requests for, where an empty SSID matches any network.
+///
+/// Without any SSIDs a scan is passive.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ScanSsids {
+    ssids: Vec<Vec<u8>>,
+}
+
+impl_attribute_list!(ScanSsids { ssids: Vec<u8> }: NL80211_ATTR_SCAN_SSIDS);
+
+impl ScanSsids {
+    pub fn new<I: IntoIterator<Item = Vec<u8>>>(ssids: I) -> Self {
+        Self {
+            ssids: ssids.into_iter().collect(),
         }
     }
 
-    fn command(&self) -> u8 {
-        match self {
-            Self::GetInterface(_) => NL80211_CMD_GET_INTERFACE,
+    /// Probe for any network.
+    pub fn wildcard() -> Self {
+        Self::new(vec![Vec::new()])
+    }
+}
+
+/// Frequencies to scan in MHz, instead of all supported ones.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ScanFrequencies {
+    frequencies: Vec<u32>,
+}
+
+impl_attribute_list!(ScanFrequencies { frequencies: u32 }: NL80211_ATTR_SCAN_FREQUENCIES);
+
+impl ScanFrequencies {
+    pub fn new<I: IntoIterator<Item = u32>>(frequencies: I) -> Self {
+        Self {
+            frequencies: frequencies.into_iter().collect(),
         }
     }
+}
+
+/// Raw information elements, such as ones to add to probe requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InformationElements(Vec<u8>);
+
+impl_wrapped_attribute!(InformationElements(Vec<u8>): NL80211_ATTR_IE);
+
+impl InformationElements {
+    pub fn new(elements: Vec<u8>) -> Self {
+        Self(elements)
+    }
+}
+
+/// Request to scan with `NL80211_CMD_TRIGGER_SCAN`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerScan {
+    pub index: InterfaceIndex,
+    pub ssids: Option<ScanSsids>,
+    pub frequencies: Option<ScanFrequencies>,
+    pub extra_ies: Option<InformationElements>,
+}
+
+impl_nested_attribute! {
+    TriggerScan:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_SCAN_SSIDS => ssids: Option<ScanSsids>,
+        NL80211_ATTR_SCAN_FREQUENCIES => frequencies: Option<ScanFrequencies>,
+        NL80211_ATTR_IE => extra_ies: Option<InformationElements>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Bssid([u8; 6]);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BssFrequency(u32);
+
+/// Timing synchronization function timer of the BSS in microseconds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tsf(u64);
+
+/// Beacon interval in time units of 1024 microseconds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BeaconInterval(u16);
+
+/// Capability information field of the beacon or probe response.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Capability(u16);
+
+/// Information elements of the last frame received from the BSS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BssInformationElements(Vec<u8>);
+
+/// Information elements of the last beacon, if it differs from the last frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeaconInformationElements(Vec<u8>);
+
+/// Signal strength in mBm (100 times dBm).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SignalMbm(i32);
+
+/// Signal strength in unspecified units from 0 to 100, for drivers not reporting dBm.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SignalUnspecified(u8);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SeenMsAgo(u32);
+
+impl_wrapped_attribute!(Bssid([u8; 6]): NL80211_BSS_BSSID);
+impl_wrapped_attribute!(BssFrequency(u32): NL80211_BSS_FREQUENCY);
+impl_wrapped_attribute!(Tsf(u64): NL80211_BSS_TSF);
+impl_wrapped_attribute!(BeaconInterval(u16): NL80211_BSS_BEACON_INTERVAL);
+impl_wrapped_attribute!(Capability(u16): NL80211_BSS_CAPABILITY);
+impl_wrapped_attribute!(BssInformationElements(Vec<u8>): NL80211_BSS_INFORMATION_ELEMENTS);
+impl_wrapped_attribute!(BeaconInformationElements(Vec<u8>): NL80211_BSS_BEACON_IES);
+impl_wrapped_attribute!(SignalMbm(i32): NL80211_BSS_SIGNAL_MBM);
+impl_wrapped_attribute!(SignalUnspecified(u8): NL80211_BSS_SIGNAL_UNSPEC);
+impl_wrapped_attribute!(SeenMsAgo(u32): NL80211_BSS_SEEN_MS_AGO);
+
+/// A network found by a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BssInfo {
+    pub bssid: Bssid,
+    pub tsf: Option<Tsf>,
+    pub ies: Option<BssInformationElements>,
+    pub beacon_ies: Option<BeaconInformationElements>,
+    pub beacon_interval: Option<BeaconInterval>,
+    pub capability: Option<Capability>,
+    pub frequency: BssFrequency,
+    pub seen_ms_ago: Option<SeenMsAgo>,
+    pub signal_mbm: Option<SignalMbm>,
+    pub signal_unspecified: Option<SignalUnspecified>,
+}
+
+// Same order as the kernel's nl80211_send_bss()
+impl_nested_attribute! {
+    BssInfo, unknown => skip:
+        NL80211_BSS_BSSID => bssid: Bssid,
+        NL80211_BSS_TSF => tsf: Option<Tsf>,
+        NL80211_BSS_INFORMATION_ELEMENTS => ies: Option<BssInformationElements>,
+        NL80211_BSS_BEACON_IES => beacon_ies: Option<BeaconInformationElements>,
+        NL80211_BSS_BEACON_INTERVAL => beacon_interval: Option<BeaconInterval>,
+        NL80211_BSS_CAPABILITY => capability: Option<Capability>,
+        NL80211_BSS_FREQUENCY => frequency: BssFrequency,
+        NL80211_BSS_SEEN_MS_AGO => seen_ms_ago: Option<SeenMsAgo>,
+        NL80211_BSS_SIGNAL_MBM => signal_mbm: Option<SignalMbm>,
+        NL80211_BSS_SIGNAL_UNSPEC => signal_unspecified: Option<SignalUnspecified>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bss(BssInfo);
+
+impl_wrapped_attribute!(Bss(BssInfo): NL80211_ATTR_BSS);
+
+impl BssInfo {
+    pub fn bssid(&self) -> [u8; 6] {
+        self.bssid.0
+    }
+
+    pub fn mhz(&self) -> u32 {
+        self.frequency.0
+    }
+
+    /// Signal strength in dBm, if the driver reports it.
+    pub fn dbm(&self) -> Option<f32> {
+        self.signal_mbm.map(|signal| signal.0 as f32 / 100.0)
+    }
+}
+
+/// A scan being started, completed or aborted, or an entry of the scan results dump.
+///
+/// Notifications name the scanned SSIDs and frequencies, dump entries carry a BSS instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanResults {
+    pub generation: Option<Generation>,
+    pub wiphy: Option<WiphyIndex>,
+    pub index: Option<InterfaceIndex>,
+    pub wdev: Option<WirelessDevice>,
+    pub ssids: Option<ScanSsids>,
+    pub frequencies: Option<ScanFrequencies>,
+    pub ies: Option<InformationElements>,
+    pub bss: Option<Bss>,
+}
+
+impl_nested_attribute! {
+    ScanResults, unknown => skip:
+        NL80211_ATTR_GENERATION => generation: Option<Generation>,
+        NL80211_ATTR_WIPHY => wiphy: Option<WiphyIndex>,
+        NL80211_ATTR_IFINDEX => index: Option<InterfaceIndex>,
+        NL80211_ATTR_WDEV => wdev: Option<WirelessDevice>,
+        NL80211_ATTR_SCAN_SSIDS => ssids: Option<ScanSsids>,
+        NL80211_ATTR_SCAN_FREQUENCIES => frequencies: Option<ScanFrequencies>,
+        NL80211_ATTR_IE => ies: Option<InformationElements>,
+        NL80211_ATTR_BSS => bss: Option<Bss>,
+}
+
+/// A complete management frame, starting with the frame control field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFrame(Vec<u8>);
+
+/// Frame control field of a management frame with only type and subtype set.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameType(u16);
+
+/// Prefix of the frame body, starting after the header, that frames must match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameMatch(Vec<u8>);
+
+/// Identifier the kernel assigns to a transmitted frame or a remain-on-channel period.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Cookie(u64);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RxSignalDbm(i32);
+
+/// A duration in milliseconds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DurationMs(u32);
+
+/// Do not use CCK rates, i.e. 1 to 11 Mbps, to transmit the frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TxNoCckRate;
+
+/// Do not report whether the frame was acknowledged.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DontWaitForAck;
+
+/// The transmitted frame was acknowledged by its receiver.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ack;
+
+impl_wrapped_attribute!(RawFrame(Vec<u8>): NL80211_ATTR_FRAME);
+impl_wrapped_attribute!(FrameType(u16): NL80211_ATTR_FRAME_TYPE);
+impl_wrapped_attribute!(FrameMatch(Vec<u8>): NL80211_ATTR_FRAME_MATCH);
+impl_wrapped_attribute!(Cookie(u64): NL80211_ATTR_COOKIE);
+impl_wrapped_attribute!(RxSignalDbm(i32): NL80211_ATTR_RX_SIGNAL_DBM);
+impl_wrapped_attribute!(DurationMs(u32): NL80211_ATTR_DURATION);
+impl_wrapped_attribute!(TxNoCckRate: NL80211_ATTR_TX_NO_CCK_RATE);
+impl_wrapped_attribute!(DontWaitForAck: NL80211_ATTR_DONT_WAIT_FOR_ACK);
+impl_wrapped_attribute!(Ack: NL80211_ATTR_ACK);
+
+impl DurationMs {
+    pub fn new(milliseconds: u32) -> Self {
+        Self(milliseconds)
+    }
+}
+
+impl RawFrame {
+    pub fn new(frame: Vec<u8>) -> Self {
+        Self(frame)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl FrameType {
+    pub fn new(frame_control: u16) -> Self {
+        Self(frame_control)
+    }
+}
+
+impl FrameMatch {
+    pub fn new(prefix: Vec<u8>) -> Self {
+        Self(prefix)
+    }
+}
+
+/// Request with `NL80211_CMD_REGISTER_FRAME` to receive management frames on an interface.
+///
+/// Matching frames are then passed to the registering socket as `NL80211_CMD_FRAME`
+/// notifications instead of being handled by the kernel. The registration lasts until the socket
+/// is closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterFrame {
+    pub index: InterfaceIndex,
+    pub frame_type: FrameType,
+    pub match_bytes: FrameMatch,
+}
+
+impl_nested_attribute! {
+    RegisterFrame:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_FRAME_TYPE => frame_type: FrameType,
+        NL80211_ATTR_FRAME_MATCH => match_bytes: FrameMatch,
+}
+
+/// A management frame received on a registration, or the reply to transmitting one.
+///
+/// Received frames come with the frequency and signal strength they were received with,
+/// replies only carry the cookie identifying the transmitted frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameInfo {
+    pub wiphy: Option<WiphyIndex>,
+    pub index: Option<InterfaceIndex>,
+    pub wdev: Option<WirelessDevice>,
+    pub frequency: Option<Frequency>,
+    pub signal: Option<RxSignalDbm>,
+    pub frame: Option<RawFrame>,
+    pub cookie: Option<Cookie>,
+}
+
+// Same order as the kernel's nl80211_send_mgmt()
+impl_nested_attribute! {
+    FrameInfo, unknown => skip:
+        NL80211_ATTR_WIPHY => wiphy: Option<WiphyIndex>,
+        NL80211_ATTR_IFINDEX => index: Option<InterfaceIndex>,
+        NL80211_ATTR_WDEV => wdev: Option<WirelessDevice>,
+        NL80211_ATTR_WIPHY_FREQ => frequency: Option<Frequency>,
+        NL80211_ATTR_RX_SIGNAL_DBM => signal: Option<RxSignalDbm>,
+        NL80211_ATTR_FRAME => frame: Option<RawFrame>,
+        NL80211_ATTR_COOKIE => cookie: Option<Cookie>,
+}
+
+impl FrameInfo {
+    pub fn mhz(&self) -> Option<u32> {
+        self.frequency.map(|frequency| frequency.0)
+    }
 
-    fn version(&self) -> u8 {
-        0
+    pub fn dbm(&self) -> Option<i32> {
+        self.signal.map(|signal| signal.0)
     }
+}
+
+/// Request to transmit a management frame with `NL80211_CMD_FRAME`.
+///
+/// Without a frequency the frame is sent on the channel the interface operates on. A wait time
+/// keeps the radio on the channel after transmitting, to receive a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub index: InterfaceIndex,
+    pub frequency: Option<Frequency>,
+    pub wait: Option<DurationMs>,
+    pub no_cck: Option<TxNoCckRate>,
+    pub dont_wait_for_ack: Option<DontWaitForAck>,
+    pub frame: RawFrame,
+}
+
+impl_nested_attribute! {
+    Frame:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_WIPHY_FREQ => frequency: Option<Frequency>,
+        NL80211_ATTR_DURATION => wait: Option<DurationMs>,
+        NL80211_ATTR_TX_NO_CCK_RATE => no_cck: Option<TxNoCckRate>,
+        NL80211_ATTR_DONT_WAIT_FOR_ACK => dont_wait_for_ack: Option<DontWaitForAck>,
+        NL80211_ATTR_FRAME => frame: RawFrame,
+}
+
+/// Whether a frame transmitted with `NL80211_CMD_FRAME` was acknowledged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameTxStatus {
+    pub wiphy: Option<WiphyIndex>,
+    pub index: Option<InterfaceIndex>,
+    pub wdev: Option<WirelessDevice>,
+    pub frame: Option<RawFrame>,
+    pub cookie: Cookie,
+    pub ack: Option<Ack>,
+}
+
+// Same order as the kernel's nl80211_frame_tx_status()
+impl_nested_attribute! {
+    FrameTxStatus, unknown => skip:
+        NL80211_ATTR_WIPHY => wiphy: Option<WiphyIndex>,
+        NL80211_ATTR_IFINDEX => index: Option<InterfaceIndex>,
+        NL80211_ATTR_WDEV => wdev: Option<WirelessDevice>,
+        NL80211_ATTR_FRAME => frame: Option<RawFrame>,
+        NL80211_ATTR_COOKIE => cookie: Cookie,
+        NL80211_ATTR_ACK => ack: Option<Ack>,
+}
+
+/// Request with `NL80211_CMD_REMAIN_ON_CHANNEL` to keep the radio of an interface on another
+/// channel for a while, without changing the channel the interface operates on.
+///
+/// The kernel replies with the cookie of the period, and notifies the "mlme" group once the
+/// radio is on the channel and again when the period ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemainOnChannel {
+    pub index: InterfaceIndex,
+    pub frequency: Frequency,
+    pub duration: DurationMs,
+}
+
+impl_nested_attribute! {
+    RemainOnChannel:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_WIPHY_FREQ => frequency: Frequency,
+        NL80211_ATTR_DURATION => duration: DurationMs,
+}
 
-    fn emit_attributes(&self, buffer: &mut [u8]) {
-        match self {
-            Self::GetInterface(index) => index.emit(buffer),
+impl RemainOnChannel {
+    /// Stay on the 20 MHz channel at `mhz` for `milliseconds`.
+    pub fn new(index: InterfaceIndex, mhz: u32, milliseconds: u32) -> Self {
+        Self {
+            index,
+            frequency: Frequency(mhz),
+            duration: DurationMs(milliseconds),
         }
     }
 }
 
-impl NetlinkSerializable<Nl80221TaggedMessage> for Nl80221TaggedMessage {
-    fn buffer_len(&self) -> usize {
-        4 + self.message.attribute_size()
+/// Request with `NL80211_CMD_CANCEL_REMAIN_ON_CHANNEL` to end a remain-on-channel period early.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CancelRemainOnChannel {
+    pub index: InterfaceIndex,
+    pub cookie: Cookie,
+}
+
+impl_nested_attribute! {
+    CancelRemainOnChannel:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_COOKIE => cookie: Cookie,
+}
+
+/// A remain-on-channel period, as replied to requesting it and notified when it starts or ends.
+///
+/// The reply only carries the cookie, and the notification of the end has no duration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemainOnChannelInfo {
+    pub wiphy: Option<WiphyIndex>,
+    pub index: Option<InterfaceIndex>,
+    pub wdev: Option<WirelessDevice>,
+    pub frequency: Option<Frequency>,
+    pub channel_type: Option<ChannelType>,
+    pub cookie: Cookie,
+    pub duration: Option<DurationMs>,
+}
+
+// Same order as the kernel's nl80211_send_remain_on_chan_event()
+impl_nested_attribute! {
+    RemainOnChannelInfo, unknown => skip:
+        NL80211_ATTR_WIPHY => wiphy: Option<WiphyIndex>,
+        NL80211_ATTR_IFINDEX => index: Option<InterfaceIndex>,
+        NL80211_ATTR_WDEV => wdev: Option<WirelessDevice>,
+        NL80211_ATTR_WIPHY_FREQ => frequency: Option<Frequency>,
+        NL80211_ATTR_WIPHY_CHANNEL_TYPE => channel_type: Option<ChannelType>,
+        NL80211_ATTR_COOKIE => cookie: Cookie,
+        NL80211_ATTR_DURATION => duration: Option<DurationMs>,
+}
+
+/// Beacon frame up to the TIM element, which the kernel inserts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeaconHead(Vec<u8>);
+
+/// Beacon frame after the TIM element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeaconTail(Vec<u8>);
+
+/// Interval between beacons in time units of 1024 microseconds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ApBeaconInterval(u32);
+
+/// Number of beacons between DTIM beacons.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DtimPeriod(u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ssid(Vec<u8>);
+
+/// Probe response template, for drivers answering probe requests by themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeResponse(Vec<u8>);
+
+kernel_enum! {
+    /// Whether and how the SSID is left out of beacons.
+    pub enum HiddenSsid: u32 {
+        NotInUse = NL80211_HIDDEN_SSID_NOT_IN_USE,
+        ZeroLength = NL80211_HIDDEN_SSID_ZERO_LEN,
+        ZeroContents = NL80211_HIDDEN_SSID_ZERO_CONTENTS,
+    }
+}
+
+kernel_enum! {
+    pub enum AuthType: u32 {
+        OpenSystem = NL80211_AUTHTYPE_OPEN_SYSTEM,
+        SharedKey = NL80211_AUTHTYPE_SHARED_KEY,
+        Ft = NL80211_AUTHTYPE_FT,
+        NetworkEap = NL80211_AUTHTYPE_NETWORK_EAP,
+        Sae = NL80211_AUTHTYPE_SAE,
+        Automatic = NL80211_AUTHTYPE_AUTOMATIC,
+    }
+}
+
+impl_wrapped_attribute!(BeaconHead(Vec<u8>): NL80211_ATTR_BEACON_HEAD);
+impl_wrapped_attribute!(BeaconTail(Vec<u8>): NL80211_ATTR_BEACON_TAIL);
+impl_wrapped_attribute!(ApBeaconInterval(u32): NL80211_ATTR_BEACON_INTERVAL);
+impl_wrapped_attribute!(DtimPeriod(u32): NL80211_ATTR_DTIM_PERIOD);
+impl_wrapped_attribute!(Ssid(Vec<u8>): NL80211_ATTR_SSID);
+impl_wrapped_attribute!(ProbeResponse(Vec<u8>): NL80211_ATTR_PROBE_RESP);
+impl_wrapped_attribute!(HiddenSsid as u32: NL80211_ATTR_HIDDEN_SSID);
+impl_wrapped_attribute!(AuthType as u32: NL80211_ATTR_AUTH_TYPE);
+
+impl BeaconHead {
+    pub fn new(head: Vec<u8>) -> Self {
+        Self(head)
+    }
+}
+
+impl BeaconTail {
+    pub fn new(tail: Vec<u8>) -> Self {
+        Self(tail)
+    }
+}
+
+impl ApBeaconInterval {
+    pub fn new(time_units: u32) -> Self {
+        Self(time_units)
     }
+}
 
-    fn message_type(&self) -> u16 {
-        self.family_id.clone().into()
+impl DtimPeriod {
+    pub fn new(beacons: u32) -> Self {
+        Self(beacons)
     }
+}
 
-    fn serialize(&self, buffer: &mut [u8]) {
-        buffer[0] = self.message.command();
-        buffer[1] = self.message.version();
-        buffer[2] = 0;
-        buffer[3] = 0;
+impl Ssid {
+    pub fn new(ssid: Vec<u8>) -> Self {
+        Self(ssid)
+    }
+}
 
-        self.message.emit_attributes(&mut buffer[4..]);
+impl ProbeResponse {
+    pub fn new(template: Vec<u8>) -> Self {
+        Self(template)
     }
 }
 
-impl From<Nl80221TaggedMessage> for NetlinkPayload<Nl80221TaggedMessage> {
-    fn from(message: Nl80221TaggedMessage) -> Self {
-        Self::InnerMessage(message)
+/// Request to start operating as an access point with `NL80211_CMD_START_AP`.
+///
+/// The beacon is given as a frame split around the TIM element. The SSID is needed in addition
+/// to the one in the beacon, which may be hidden. Without a channel, the interface's current
+/// one is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartAp {
+    pub index: InterfaceIndex,
+    pub beacon_interval: ApBeaconInterval,
+    pub dtim_period: DtimPeriod,
+    pub beacon_head: BeaconHead,
+    pub beacon_tail: Option<BeaconTail>,
+    pub ssid: Ssid,
+    pub hidden_ssid: Option<HiddenSsid>,
+    pub auth_type: Option<AuthType>,
+    pub frequency: Option<Frequency>,
+    pub channel_type: Option<ChannelType>,
+    pub channel_width: Option<ChannelWidth>,
+    pub center_frequency1: Option<CenterFrequency1>,
+    pub probe_response: Option<ProbeResponse>,
+}
+
+impl_nested_attribute! {
+    StartAp:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_BEACON_INTERVAL => beacon_interval: ApBeaconInterval,
+        NL80211_ATTR_DTIM_PERIOD => dtim_period: DtimPeriod,
+        NL80211_ATTR_BEACON_HEAD => beacon_head: BeaconHead,
+        NL80211_ATTR_BEACON_TAIL => beacon_tail: Option<BeaconTail>,
+        NL80211_ATTR_SSID => ssid: Ssid,
+        NL80211_ATTR_HIDDEN_SSID => hidden_ssid: Option<HiddenSsid>,
+        NL80211_ATTR_AUTH_TYPE => auth_type: Option<AuthType>,
+        NL80211_ATTR_WIPHY_FREQ => frequency: Option<Frequency>,
+        NL80211_ATTR_WIPHY_CHANNEL_TYPE => channel_type: Option<ChannelType>,
+        NL80211_ATTR_CHANNEL_WIDTH => channel_width: Option<ChannelWidth>,
+        NL80211_ATTR_CENTER_FREQ1 => center_frequency1: Option<CenterFrequency1>,
+        NL80211_ATTR_PROBE_RESP => probe_response: Option<ProbeResponse>,
+}
+
+/// Request to update the beacon of a running access point with `NL80211_CMD_SET_BEACON`.
+///
+/// Parts left out stay as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetBeacon {
+    pub index: InterfaceIndex,
+    pub beacon_head: Option<BeaconHead>,
+    pub beacon_tail: Option<BeaconTail>,
+    pub probe_response: Option<ProbeResponse>,
+}
+
+impl_nested_attribute! {
+    SetBeacon:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_BEACON_HEAD => beacon_head: Option<BeaconHead>,
+        NL80211_ATTR_BEACON_TAIL => beacon_tail: Option<BeaconTail>,
+        NL80211_ATTR_PROBE_RESP => probe_response: Option<ProbeResponse>,
+}
+
+/// Milliseconds since the last frame from the station.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InactiveTime(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RxBytes(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TxBytes(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RxBytes64(u64);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TxBytes64(u64);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RxPackets(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TxPackets(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TxRetries(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TxFailed(u32);
+
+/// Signal strength of the last frame from the station in dBm.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StationSignal(i8);
+
+/// Average signal strength of frames from the station in dBm.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StationSignalAvg(i8);
+
+/// Seconds since the station associated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ConnectedTime(u32);
+
+/// Time since boot at which the station associated, in nanoseconds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AssociatedAt(u64);
+
+impl_wrapped_attribute!(InactiveTime(u32): NL80211_STA_INFO_INACTIVE_TIME);
+impl_wrapped_attribute!(RxBytes(u32): NL80211_STA_INFO_RX_BYTES);
+impl_wrapped_attribute!(TxBytes(u32): NL80211_STA_INFO_TX_BYTES);
+impl_wrapped_attribute!(RxBytes64(u64): NL80211_STA_INFO_RX_BYTES64);
+impl_wrapped_attribute!(TxBytes64(u64): NL80211_STA_INFO_TX_BYTES64);
+impl_wrapped_attribute!(RxPackets(u32): NL80211_STA_INFO_RX_PACKETS);
+impl_wrapped_attribute!(TxPackets(u32): NL80211_STA_INFO_TX_PACKETS);
+impl_wrapped_attribute!(TxRetries(u32): NL80211_STA_INFO_TX_RETRIES);
+impl_wrapped_attribute!(TxFailed(u32): NL80211_STA_INFO_TX_FAILED);
+impl_wrapped_attribute!(StationSignal(i8): NL80211_STA_INFO_SIGNAL);
+impl_wrapped_attribute!(StationSignalAvg(i8): NL80211_STA_INFO_SIGNAL_AVG);
+impl_wrapped_attribute!(ConnectedTime(u32): NL80211_STA_INFO_CONNECTED_TIME);
+impl_wrapped_attribute!(AssociatedAt(u64): NL80211_STA_INFO_ASSOC_AT_BOOTTIME);
+
+/// Statistics of a station, as far as the driver keeps them.
+///
+/// Byte counters come in 32 and 64 bits, older kernels only send the former.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StationStats {
+    pub connected_time: Option<ConnectedTime>,
+    pub inactive_time: Option<InactiveTime>,
+    pub associated_at: Option<AssociatedAt>,
+    pub rx_bytes: Option<RxBytes>,
+    pub rx_bytes64: Option<RxBytes64>,
+    pub tx_bytes: Option<TxBytes>,
+    pub tx_bytes64: Option<TxBytes64>,
+    pub rx_packets: Option<RxPackets>,
+    pub tx_packets: Option<TxPackets>,
+    pub tx_retries: Option<TxRetries>,
+    pub tx_failed: Option<TxFailed>,
+    pub signal: Option<StationSignal>,
+    pub signal_avg: Option<StationSignalAvg>,
+}
+
+// Same order as the kernel's nl80211_send_station()
+impl_nested_attribute! {
+    StationStats, unknown => skip:
+        NL80211_STA_INFO_CONNECTED_TIME => connected_time: Option<ConnectedTime>,
+        NL80211_STA_INFO_INACTIVE_TIME => inactive_time: Option<InactiveTime>,
+        NL80211_STA_INFO_ASSOC_AT_BOOTTIME => associated_at: Option<AssociatedAt>,
+        NL80211_STA_INFO_RX_BYTES => rx_bytes: Option<RxBytes>,
+        NL80211_STA_INFO_RX_BYTES64 => rx_bytes64: Option<RxBytes64>,
+        NL80211_STA_INFO_TX_BYTES => tx_bytes: Option<TxBytes>,
+        NL80211_STA_INFO_TX_BYTES64 => tx_bytes64: Option<TxBytes64>,
+        NL80211_STA_INFO_RX_PACKETS => rx_packets: Option<RxPackets>,
+        NL80211_STA_INFO_TX_PACKETS => tx_packets: Option<TxPackets>,
+        NL80211_STA_INFO_TX_RETRIES => tx_retries: Option<TxRetries>,
+        NL80211_STA_INFO_TX_FAILED => tx_failed: Option<TxFailed>,
+        NL80211_STA_INFO_SIGNAL => signal: Option<StationSignal>,
+        NL80211_STA_INFO_SIGNAL_AVG => signal_avg: Option<StationSignalAvg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaInfo(StationStats);
+
+impl_wrapped_attribute!(StaInfo(StationStats): NL80211_ATTR_STA_INFO);
+
+impl StationStats {
+    /// Seconds since the station associated.
+    pub fn connected_secs(&self) -> Option<u32> {
+        self.connected_time.map(|time| time.0)
+    }
+
+    pub fn rx_bytes(&self) -> Option<u64> {
+        self.rx_bytes64
+            .map(|bytes| bytes.0)
+            .or_else(|| self.rx_bytes.map(|bytes| bytes.0.into()))
+    }
+
+    pub fn tx_bytes(&self) -> Option<u64> {
+        self.tx_bytes64
+            .map(|bytes| bytes.0)
+            .or_else(|| self.tx_bytes.map(|bytes| bytes.0.into()))
+    }
+
+    pub fn rx_packets(&self) -> Option<u32> {
+        self.rx_packets.map(|packets| packets.0)
+    }
+
+    pub fn tx_packets(&self) -> Option<u32> {
+        self.tx_packets.map(|packets| packets.0)
+    }
+
+    /// Signal strength of the last frame from the station in dBm.
+    pub fn dbm(&self) -> Option<i8> {
+        self.signal.map(|signal| signal.0)
+    }
+}
+
+/// A station associated to an interface, as replied to `GetStation`, or notified to the "mlme"
+/// group when it associates or leaves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StationInfo {
+    pub index: Option<InterfaceIndex>,
+    pub mac: MacAddress,
+    pub generation: Option<Generation>,
+    pub stats: Option<StaInfo>,
+    /// Information elements of the association request, in notifications of new stations.
+    pub ies: Option<InformationElements>,
+}
+
+// Same order as the kernel's nl80211_send_station()
+impl_nested_attribute! {
+    StationInfo, unknown => skip:
+        NL80211_ATTR_IFINDEX => index: Option<InterfaceIndex>,
+        NL80211_ATTR_MAC => mac: MacAddress,
+        NL80211_ATTR_GENERATION => generation: Option<Generation>,
+        NL80211_ATTR_STA_INFO => stats: Option<StaInfo>,
+        NL80211_ATTR_IE => ies: Option<InformationElements>,
+}
+
+impl StationInfo {
+    pub fn mac(&self) -> [u8; 6] {
+        self.mac.0
+    }
+
+    pub fn stats(&self) -> Option<&StationStats> {
+        self.stats.as_ref().map(|stats| &stats.0)
+    }
+}
+
+/// Request for a single station of an interface with `NL80211_CMD_GET_STATION`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StationQuery {
+    pub index: InterfaceIndex,
+    pub mac: MacAddress,
+}
+
+impl_nested_attribute! {
+    StationQuery:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_MAC => mac: MacAddress,
+}
+
+/// IEEE 802.11 reason code sent to a station when it is disconnected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReasonCode(u16);
+
+impl_wrapped_attribute!(ReasonCode(u16): NL80211_ATTR_REASON_CODE);
+
+impl ReasonCode {
+    pub fn new(code: u16) -> Self {
+        Self(code)
+    }
+}
+
+/// Request with `NL80211_CMD_DEL_STATION` to deauthenticate a station from an access point.
+///
+/// Without a MAC address, every station of the interface is removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelStation {
+    pub index: InterfaceIndex,
+    pub mac: Option<MacAddress>,
+    pub reason: Option<ReasonCode>,
+}
+
+impl_nested_attribute! {
+    DelStation:
+        NL80211_ATTR_IFINDEX => index: InterfaceIndex,
+        NL80211_ATTR_MAC => mac: Option<MacAddress>,
+        NL80211_ATTR_REASON_CODE => reason: Option<ReasonCode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WiphyName(String);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MaxScanSsids(u8);
+
+/// Cipher suites supported by the radio, as IEEE 802.11 suite selectors.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CipherSuites(Vec<u32>);
+
+/// Flag requesting a wiphy dump split into several messages per radio.
+///
+/// Without it, the description of radios with many channels or commands gets truncated to what
+/// fits into a single message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SplitWiphyDump;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChannelFrequency(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChannelDisabled;
+
+/// The channel may not be used to initiate radiation, such as sending beacons or probe requests.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChannelNoIr;
+
+/// The channel requires radar detection.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChannelRadar;
+
+/// Maximum transmission power on the channel in mBm.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChannelMaxTxPower(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CombinationMaxInterfaces(u32);
+
+/// All interfaces of the combination must use the same beacon interval.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CombinationBeaconIntervalMatch;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CombinationChannels(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CombinationRadarWidths(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CombinationRadarRegions(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LimitMaxInterfaces(u32);
+
+kernel_enum! {
+    pub enum Band: u32 {
+        TwoGhz = NL80211_BAND_2GHZ,
+        FiveGhz = NL80211_BAND_5GHZ,
+        SixtyGhz = NL80211_BAND_60GHZ,
+        SixGhz = NL80211_BAND_6GHZ,
+        S1Ghz = NL80211_BAND_S1GHZ,
+        Lc = NL80211_BAND_LC,
+    }
+}
+
+impl_wrapped_attribute!(WiphyName(String): NL80211_ATTR_WIPHY_NAME);
+impl_wrapped_attribute!(MaxScanSsids(u8): NL80211_ATTR_MAX_NUM_SCAN_SSIDS);
+impl_wrapped_attribute!(CipherSuites(Vec<u32>): NL80211_ATTR_CIPHER_SUITES);
+impl_wrapped_attribute!(SplitWiphyDump: NL80211_ATTR_SPLIT_WIPHY_DUMP);
+
+impl_wrapped_attribute!(ChannelFrequency(u32): NL80211_FREQUENCY_ATTR_FREQ);
+impl_wrapped_attribute!(ChannelDisabled: NL80211_FREQUENCY_ATTR_DISABLED);
+impl_wrapped_attribute!(ChannelNoIr: NL80211_FREQUENCY_ATTR_NO_IR);
+impl_wrapped_attribute!(ChannelRadar: NL80211_FREQUENCY_ATTR_RADAR);
+impl_wrapped_attribute!(ChannelMaxTxPower(u32): NL80211_FREQUENCY_ATTR_MAX_TX_POWER);
+
+impl_wrapped_attribute!(CombinationMaxInterfaces(u32): NL80211_IFACE_COMB_MAXNUM);
+impl_wrapped_attribute!(CombinationBeaconIntervalMatch: NL80211_IFACE_COMB_STA_AP_BI_MATCH);
+impl_wrapped_attribute!(CombinationChannels(u32): NL80211_IFACE_COMB_NUM_CHANNELS);
+impl_wrapped_attribute!(CombinationRadarWidths(u32): NL80211_IFACE_COMB_RADAR_DETECT_WIDTHS);
+impl_wrapped_attribute!(CombinationRadarRegions(u32): NL80211_IFACE_COMB_RADAR_DETECT_REGIONS);
+impl_wrapped_attribute!(LimitMaxInterfaces(u32): NL80211_IFACE_LIMIT_MAX);
+
+/// A channel of a band and the regulatory restrictions on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Channel {
+    pub frequency: ChannelFrequency,
+    pub disabled: Option<ChannelDisabled>,
+    pub no_ir: Option<ChannelNoIr>,
+    pub radar: Option<ChannelRadar>,
+    pub max_tx_power: Option<ChannelMaxTxPower>,
+}
+
+impl_nested_attribute! {
+    Channel, unknown => skip:
+        NL80211_FREQUENCY_ATTR_FREQ => frequency: ChannelFrequency,
+        NL80211_FREQUENCY_ATTR_DISABLED => disabled: Option<ChannelDisabled>,
+        NL80211_FREQUENCY_ATTR_NO_IR => no_ir: Option<ChannelNoIr>,
+        NL80211_FREQUENCY_ATTR_RADAR => radar: Option<ChannelRadar>,
+        NL80211_FREQUENCY_ATTR_MAX_TX_POWER => max_tx_power: Option<ChannelMaxTxPower>,
+}
+
+impl Channel {
+    /// Frequency of the channel in MHz.
+    pub fn mhz(&self) -> u32 {
+        self.frequency.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChannelList {
+    channels: Vec<Channel>,
+}
+
+impl_attribute_list!(ChannelList { channels: Channel }: NL80211_BAND_ATTR_FREQS);
+
+impl ChannelList {
+    pub fn iter(&self) -> impl Iterator<Item = &Channel> {
+        self.channels.iter()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BandInfo {
+    pub channels: ChannelList,
+}
+
+impl_nested_attribute! {
+    BandInfo, unknown => skip:
+        NL80211_BAND_ATTR_FREQS => channels: ChannelList = ChannelList::default(),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BandList {
+    bands: Vec<(Band, BandInfo)>,
+}
+
+impl_attribute_map!(BandList { bands: Band => BandInfo }: NL80211_ATTR_WIPHY_BANDS);
+
+impl BandList {
+    pub fn iter(&self) -> impl Iterator<Item = &(Band, BandInfo)> {
+        self.bands.iter()
+    }
+
+    /// Add the channels of `other`, which split dumps spread over several messages.
+    fn merge(&mut self, other: BandList) {
+        for (band, info) in other.bands {
+            match self.bands.iter_mut().find(|(known, _)| *known == band) {
+                Some((_, known)) => known.channels.channels.extend(info.channels.channels),
+                None => self.bands.push((band, info)),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SupportedInterfaceTypes {
+    iftypes: Vec<(InterfaceType, ())>,
+}
+
+impl_attribute_map!(SupportedInterfaceTypes { iftypes: InterfaceType => () }: NL80211_ATTR_SUPPORTED_IFTYPES);
+
+impl SupportedInterfaceTypes {
+    pub fn contains(&self, iftype: InterfaceType) -> bool {
+        self.iftypes
+            .iter()
+            .any(|(supported, _)| *supported == iftype)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SupportedCommands {
+    commands: Vec<u32>,
+}
+
+impl_attribute_list!(SupportedCommands { commands: u32 }: NL80211_ATTR_SUPPORTED_COMMANDS);
+
+impl SupportedCommands {
+    pub fn contains(&self, command: u8) -> bool {
+        self.commands.contains(&command.into())
+    }
+}
+
+/// Management frame types, each given as frame control field with type and subtype set.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FrameTypeList(Vec<u16>);
+
+impl FrameTypeList {
+    fn to_attributes(&self) -> Vec<RawAttribute> {
+        self.0
+            .iter()
+            .map(|frame_type| RawAttribute::u16(NL80211_ATTR_FRAME_TYPE, *frame_type))
+            .collect()
+    }
+
+    pub fn contains(&self, frame_type: u16) -> bool {
+        self.0.contains(&frame_type)
+    }
+}
+
+impl AttributeValue for FrameTypeList {
+    const NESTED: bool = true;
+
+    fn value_len(&self) -> usize {
+        self.to_attributes().as_slice().buffer_len()
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        self.to_attributes().as_slice().emit(buffer)
+    }
+
+    fn parse_value(value: &[u8]) -> Result<Self, DecodeError> {
+        NlasIterator::new(value)
+            .filter_map(|attribute| match attribute {
+                Ok(attribute) if attribute.kind() == NL80211_ATTR_FRAME_TYPE => {
+                    Some(parsers::parse_u16(attribute.value()))
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<Result<_, _>>()
+            .map(FrameTypeList)
+    }
+}
+
+/// Management frames that can be transmitted with `NL80211_CMD_FRAME`, per interface type.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TxFrameTypes {
+    iftypes: Vec<(InterfaceType, FrameTypeList)>,
+}
+
+/// Management frames that can be registered for with `NL80211_CMD_REGISTER_FRAME`, per interface
+/// type.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RxFrameTypes {
+    iftypes: Vec<(InterfaceType, FrameTypeList)>,
+}
+
+impl_attribute_map!(TxFrameTypes { iftypes: InterfaceType => FrameTypeList }: NL80211_ATTR_TX_FRAME_TYPES);
+impl_attribute_map!(RxFrameTypes { iftypes: InterfaceType => FrameTypeList }: NL80211_ATTR_RX_FRAME_TYPES);
+
+impl TxFrameTypes {
+    pub fn contains(&self, iftype: InterfaceType, frame_type: u16) -> bool {
+        self.iftypes
+            .iter()
+            .any(|(known, frame_types)| *known == iftype && frame_types.contains(frame_type))
+    }
+}
+
+impl RxFrameTypes {
+    pub fn contains(&self, iftype: InterfaceType, frame_type: u16) -> bool {
+        self.iftypes
+            .iter()
+            .any(|(known, frame_types)| *known == iftype && frame_types.contains(frame_type))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LimitInterfaceTypes {
+    iftypes: Vec<(InterfaceType, ())>,
+}
+
+impl_attribute_map!(LimitInterfaceTypes { iftypes: InterfaceType => () }: NL80211_IFACE_LIMIT_TYPES);
+
+/// At most `max` interfaces of the given types can exist at the same time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceLimit {
+    pub max: LimitMaxInterfaces,
+    pub iftypes: LimitInterfaceTypes,
+}
+
+impl_nested_attribute! {
+    InterfaceLimit, unknown => skip:
+        NL80211_IFACE_LIMIT_MAX => max: LimitMaxInterfaces,
+        NL80211_IFACE_LIMIT_TYPES => iftypes: LimitInterfaceTypes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InterfaceLimitList {
+    limits: Vec<InterfaceLimit>,
+}
+
+impl_attribute_list!(InterfaceLimitList { limits: InterfaceLimit }: NL80211_IFACE_COMB_LIMITS);
+
+/// A set of interfaces the radio can operate concurrently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceCombination {
+    pub limits: InterfaceLimitList,
+    pub max_interfaces: CombinationMaxInterfaces,
+    pub beacon_interval_match: Option<CombinationBeaconIntervalMatch>,
+    pub channels: CombinationChannels,
+    pub radar_widths: Option<CombinationRadarWidths>,
+    pub radar_regions: Option<CombinationRadarRegions>,
+}
+
+impl_nested_attribute! {
+    InterfaceCombination, unknown => skip:
+        NL80211_IFACE_COMB_LIMITS => limits: InterfaceLimitList,
+        NL80211_IFACE_COMB_MAXNUM => max_interfaces: CombinationMaxInterfaces,
+        NL80211_IFACE_COMB_STA_AP_BI_MATCH => beacon_interval_match: Option<CombinationBeaconIntervalMatch>,
+        NL80211_IFACE_COMB_NUM_CHANNELS => channels: CombinationChannels,
+        NL80211_IFACE_COMB_RADAR_DETECT_WIDTHS => radar_widths: Option<CombinationRadarWidths>,
+        NL80211_IFACE_COMB_RADAR_DETECT_REGIONS => radar_regions: Option<CombinationRadarRegions>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InterfaceCombinationList {
+    combinations: Vec<InterfaceCombination>,
+}
+
+impl_attribute_list!(InterfaceCombinationList { combinations: InterfaceCombination }: NL80211_ATTR_INTERFACE_COMBINATIONS);
+
+/// Filter and options for `NL80211_CMD_GET_WIPHY`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WiphyQuery {
+    pub wiphy: Option<WiphyIndex>,
+    pub split: Option<SplitWiphyDump>,
+}
+
+impl_nested_attribute! {
+    WiphyQuery, unknown => skip:
+        NL80211_ATTR_WIPHY => wiphy: Option<WiphyIndex>,
+        NL80211_ATTR_SPLIT_WIPHY_DUMP => split: Option<SplitWiphyDump>,
+}
+
+/// Capabilities of a physical radio, as reported by `NL80211_CMD_GET_WIPHY`.
+///
+/// A split dump describes each radio in several messages, which are each parsed into a partial
+/// `Wiphy` and combined with [`Wiphy::merge`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wiphy {
+    pub index: WiphyIndex,
+    pub name: WiphyName,
+    pub generation: Option<Generation>,
+    pub max_scan_ssids: Option<MaxScanSsids>,
+    pub cipher_suites: CipherSuites,
+    pub iftypes: SupportedInterfaceTypes,
+    pub bands: BandList,
+    pub commands: SupportedCommands,
+    pub tx_frame_types: TxFrameTypes,
+    pub rx_frame_types: RxFrameTypes,
+    pub interface_combinations: InterfaceCombinationList,
+}
+
+// Same order as the kernel's nl80211_send_wiphy()
+impl_nested_attribute! {
+    Wiphy, unknown => skip:
+        NL80211_ATTR_WIPHY => index: WiphyIndex,
+        NL80211_ATTR_WIPHY_NAME => name: WiphyName,
+        NL80211_ATTR_GENERATION => generation: Option<Generation>,
+        NL80211_ATTR_MAX_NUM_SCAN_SSIDS => max_scan_ssids: Option<MaxScanSsids>,
+        NL80211_ATTR_CIPHER_SUITES => cipher_suites: CipherSuites = CipherSuites::default(),
+        NL80211_ATTR_SUPPORTED_IFTYPES => iftypes: SupportedInterfaceTypes = SupportedInterfaceTypes::default(),
+        NL80211_ATTR_WIPHY_BANDS => bands: BandList = BandList::default(),
+        NL80211_ATTR_SUPPORTED_COMMANDS => commands: SupportedCommands = SupportedCommands::default(),
+        NL80211_ATTR_TX_FRAME_TYPES => tx_frame_types: TxFrameTypes = TxFrameTypes::default(),
+        NL80211_ATTR_RX_FRAME_TYPES => rx_frame_types: RxFrameTypes = RxFrameTypes::default(),
+        NL80211_ATTR_INTERFACE_COMBINATIONS => interface_combinations: InterfaceCombinationList = InterfaceCombinationList::default(),
+}
+
+impl Wiphy {
+    /// Add the capabilities from another message of a split dump for the same radio.
+    pub fn merge(&mut self, other: Wiphy) {
+        self.generation = other.generation.or(self.generation);
+        self.max_scan_ssids = other.max_scan_ssids.or(self.max_scan_ssids);
+        self.cipher_suites.0.extend(other.cipher_suites.0);
+        self.iftypes.iftypes.extend(other.iftypes.iftypes);
+        self.bands.merge(other.bands);
+        self.commands.commands.extend(other.commands.commands);
+        self.tx_frame_types
+            .iftypes
+            .extend(other.tx_frame_types.iftypes);
+        self.rx_frame_types
+            .iftypes
+            .extend(other.rx_frame_types.iftypes);
+        self.interface_combinations
+            .combinations
+            .extend(other.interface_combinations.combinations);
+    }
+
+    /// Look up the channel with center frequency `mhz` in any band.
+    pub fn channel(&self, mhz: u32) -> Option<&Channel> {
+        self.bands
+            .iter()
+            .flat_map(|(_, band)| band.channels.iter())
+            .find(|channel| channel.mhz() == mhz)
+    }
+}
+
+impl WiphyName {
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Frequency {
+    pub fn new(mhz: u32) -> Self {
+        Self(mhz)
+    }
+}
+
+impl MacAddress {
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+}
+
+impl InterfaceName {
+    pub fn new<T: Into<String>>(s: T) -> Self {
+        Self(s.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl InterfaceIndex {
+    /// Look up the index of the interface called `name` in the current network namespace.
+    pub fn from_name(name: &InterfaceName) -> io::Result<Self> {
+        let name = CString::new(name.as_str())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+            0 => Err(io::Error::last_os_error()),
+            index => Ok(Self(index)),
+        }
+    }
+
+    /// Look up the name of the interface in the current network namespace.
+    pub fn name(&self) -> io::Result<InterfaceName> {
+        let mut buffer = [0 as libc::c_char; libc::IF_NAMESIZE];
+
+        if unsafe { libc::if_indextoname(self.0, buffer.as_mut_ptr()) }.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let name = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+        Ok(InterfaceName::new(name.to_string_lossy()))
+    }
+
+    /// Bring the interface up or down by setting or clearing `IFF_UP`, like `ip link set up`.
+    ///
+    /// Access point and frame commands are refused on an interface that is down.
+    pub fn set_up(&self, up: bool) -> io::Result<()> {
+        let mut request: libc::ifreq = unsafe { std::mem::zeroed() };
+        if unsafe { libc::if_indextoname(self.0, request.ifr_name.as_mut_ptr()) }.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let result = unsafe {
+            if libc::ioctl(fd, libc::SIOCGIFFLAGS, &mut request) < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                let flags = request.ifr_ifru.ifru_flags as libc::c_int;
+                let flags = if up {
+                    flags | libc::IFF_UP
+                } else {
+                    flags & !libc::IFF_UP
+                };
+                request.ifr_ifru.ifru_flags = flags as libc::c_short;
+
+                if libc::ioctl(fd, libc::SIOCSIFFLAGS, &request) < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(())
+                }
+            }
+        };
+
+        unsafe { libc::close(fd) };
+        result
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, GenlMessage)]
+#[genl(version = 0)]
+pub enum Nl80221Message {
+    #[genl(cmd = NL80211_CMD_GET_WIPHY)]
+    GetWiphy(WiphyQuery),
+    #[genl(cmd = NL80211_CMD_NEW_WIPHY, parse)]
+    NewWiphy(Wiphy),
+    #[genl(cmd = NL80211_CMD_GET_INTERFACE)]
+    GetInterface(InterfaceIndex),
+    /// Request every wireless device on the system, to be sent with `NLM_F_DUMP`.
+    #[genl(cmd = NL80211_CMD_GET_INTERFACE)]
+    DumpInterfaces,
+    #[genl(cmd = NL80211_CMD_NEW_INTERFACE, parse)]
+    NewInterface(Interface),
+    /// Create a virtual interface, which the kernel replies to with its `NewInterface`.
+    #[genl(cmd = NL80211_CMD_NEW_INTERFACE)]
+    CreateInterface(NewInterface),
+    #[genl(cmd = NL80211_CMD_DEL_INTERFACE)]
+    DelInterface(InterfaceIndex),
+    #[genl(cmd = NL80211_CMD_SET_INTERFACE)]
+    SetInterface(SetInterface),
+    #[genl(cmd = NL80211_CMD_SET_CHANNEL)]
+    SetChannel(SetChannel),
+    /// Request the scan results of an interface, to be sent with `NLM_F_DUMP`.
+    #[genl(cmd = NL80211_CMD_GET_SCAN)]
+    GetScan(InterfaceIndex),
+    #[genl(cmd = NL80211_CMD_TRIGGER_SCAN)]
+    TriggerScan(TriggerScan),
+    /// Notification that a scan was started.
+    #[genl(cmd = NL80211_CMD_TRIGGER_SCAN, parse)]
+    ScanStarted(ScanResults),
+    #[genl(cmd = NL80211_CMD_NEW_SCAN_RESULTS, parse)]
+    NewScanResults(ScanResults),
+    #[genl(cmd = NL80211_CMD_SCAN_ABORTED, parse)]
+    ScanAborted(ScanResults),
+    #[genl(cmd = NL80211_CMD_REGISTER_FRAME)]
+    RegisterFrame(RegisterFrame),
+    #[genl(cmd = NL80211_CMD_FRAME, parse)]
+    FrameReceived(FrameInfo),
+    #[genl(cmd = NL80211_CMD_FRAME)]
+    Frame(Frame),
+    #[genl(cmd = NL80211_CMD_FRAME_TX_STATUS, parse)]
+    FrameTxStatus(FrameTxStatus),
+    #[genl(cmd = NL80211_CMD_START_AP)]
+    StartAp(StartAp),
+    #[genl(cmd = NL80211_CMD_SET_BEACON)]
+    SetBeacon(SetBeacon),
+    #[genl(cmd = NL80211_CMD_STOP_AP)]
+    StopAp(InterfaceIndex),
+    #[genl(cmd = NL80211_CMD_GET_STATION)]
+    GetStation(StationQuery),
+    /// Request every station of an interface, to be sent with `NLM_F_DUMP`.
+    #[genl(cmd = NL80211_CMD_GET_STATION)]
+    DumpStations(InterfaceIndex),
+    /// Reply to `GetStation`, or notification that a station associated.
+    #[genl(cmd = NL80211_CMD_NEW_STATION, parse)]
+    NewStation(StationInfo),
+    #[genl(cmd = NL80211_CMD_DEL_STATION)]
+    DelStation(DelStation),
+    /// Notification that a station left or was removed.
+    #[genl(cmd = NL80211_CMD_DEL_STATION, parse)]
+    StationDeleted(StationInfo),
+    #[genl(cmd = NL80211_CMD_REMAIN_ON_CHANNEL)]
+    RemainOnChannel(RemainOnChannel),
+    /// Reply to `RemainOnChannel`, or notification that the radio is on the channel.
+    #[genl(cmd = NL80211_CMD_REMAIN_ON_CHANNEL, parse)]
+    RemainOnChannelStarted(RemainOnChannelInfo),
+    #[genl(cmd = NL80211_CMD_CANCEL_REMAIN_ON_CHANNEL)]
+    CancelRemainOnChannel(CancelRemainOnChannel),
+    /// Notification that a remain-on-channel period expired or was cancelled.
+    #[genl(cmd = NL80211_CMD_CANCEL_REMAIN_ON_CHANNEL, parse)]
+    RemainOnChannelEnded(RemainOnChannelInfo),
+}
+
+#[derive(Debug, Clone)]
+pub struct Nl80221Family {
+    pub family: NewFamily,
+}
+
+impl Nl80221Family {
+    /// Look up nl80211 in `registry`, asking the kernel if it has not been resolved yet.
+    pub fn new(socket: &Socket, registry: &mut FamilyRegistry) -> Result<Self, Error> {
+        let family = registry.resolve(socket, &FamilyName::new(NL80211_FAMILY_NAME))?;
+
+        Ok(Self {
+            family: family.clone(),
+        })
+    }
+
+    /// Listen for notifications on the nl80211 multicast groups called `groups`.
+    pub fn notifications(&self, groups: &[&str]) -> Result<Notifications<Self>, Error> {
+        Notifications::new(&self.family, groups)
+    }
+
+    pub fn get_interface(
+        &self,
+        socket: &Socket,
+        index: InterfaceIndex,
+    ) -> Result<Interface, Error> {
+        match genl::request(
+            socket,
+            self.tag_message(Nl80221Message::GetInterface(index)),
+        )? {
+            Nl80221Message::NewInterface(interface) => Ok(interface),
+            reply => Err(format_err!(
+                "unexpected reply to GET_INTERFACE: {:?}",
+                reply
+            )),
+        }
+    }
+
+    /// List every wireless device on the system, including those without a netdev.
+    pub fn dump_interfaces(&self, socket: &Socket) -> Result<Vec<Interface>, Error> {
+        let interfaces = genl::dump(socket, self.tag_message(Nl80221Message::DumpInterfaces))?
+            .into_iter()
+            .filter_map(|reply| match reply {
+                Nl80221Message::NewInterface(interface) => Some(interface),
+                _ => None,
+            })
+            .collect();
+
+        Ok(interfaces)
+    }
+
+    /// Look up the interface called `name`.
+    pub fn find_interface(
+        &self,
+        socket: &Socket,
+        name: &InterfaceName,
+    ) -> Result<Option<Interface>, Error> {
+        Ok(self
+            .dump_interfaces(socket)?
+            .into_iter()
+            .find(|interface| interface.name.as_ref() == Some(name)))
+    }
+
+    /// Create a virtual interface and return it as set up by the kernel.
+    pub fn create_interface(
+        &self,
+        socket: &Socket,
+        interface: NewInterface,
+    ) -> Result<Interface, Error> {
+        match genl::request(
+            socket,
+            self.tag_message(Nl80221Message::CreateInterface(interface)),
+        )? {
+            Nl80221Message::NewInterface(interface) => Ok(interface),
+            reply => Err(format_err!(
+                "unexpected reply to NEW_INTERFACE: {:?}",
+                reply
+            )),
+        }
+    }
+
+    pub fn del_interface(&self, socket: &Socket, index: InterfaceIndex) -> Result<(), Error> {
+        genl::execute(
+            socket,
+            self.tag_message(Nl80221Message::DelInterface(index)),
+        )
+    }
+
+    pub fn set_interface(&self, socket: &Socket, request: SetInterface) -> Result<(), Error> {
+        genl::execute(
+            socket,
+            self.tag_message(Nl80221Message::SetInterface(request)),
+        )
+    }
+
+    pub fn set_channel(&self, socket: &Socket, request: SetChannel) -> Result<(), Error> {
+        genl::execute(
+            socket,
+            self.tag_message(Nl80221Message::SetChannel(request)),
+        )
+    }
+
+    /// Undo `changes` in the reverse order they were made.
+    ///
+    /// Later changes are undone even if undoing one fails, and the first error is returned.
+    pub fn restore_interface(
+        &self,
+        socket: &Socket,
+        changes: &InterfaceChanges,
+    ) -> Result<(), Error> {
+        changes
+            .undo_requests()
+            .map(|request| genl::execute(socket, self.tag_message(request.clone())))
+            .fold(Ok(()), Result::and)
+    }
+
+    /// Look up the index of the interface called `name` with nl80211 rather than the C library.
+    ///
+    /// Unlike [`InterfaceIndex::from_name`], this looks in the network namespace of `socket`,
+    /// which need not be the one the process runs in.
+    pub fn resolve_index(
+        &self,
+        socket: &Socket,
+        name: &InterfaceName,
+    ) -> Result<InterfaceIndex, Error> {
+        self.find_interface(socket, name)?
+            .and_then(|interface| interface.index)
+            .ok_or_else(|| format_err!("no wireless interface called {}", name.as_str()))
+    }
+
+    /// List the networks found by scans on interface `index`.
+    pub fn get_scan(&self, socket: &Socket, index: InterfaceIndex) -> Result<Vec<BssInfo>, Error> {
+        let networks = genl::dump(socket, self.tag_message(Nl80221Message::GetScan(index)))?
+            .into_iter()
+            .filter_map(|reply| match reply {
+                Nl80221Message::NewScanResults(results) => results.bss.map(|bss| bss.0),
+                _ => None,
+            })
+            .collect();
+
+        Ok(networks)
+    }
+
+    /// Scan as requested and wait for it to finish, returning the networks found.
+    ///
+    /// The results include networks found by earlier scans that have not expired yet.
+    pub fn scan(&self, socket: &Socket, request: TriggerScan) -> Result<Vec<BssInfo>, Error> {
+        let index = request.index;
+
+        // Listen before triggering, so the notification cannot be missed
+        let mut events = self.notifications(&[NL80211_MULTICAST_GROUP_SCAN])?;
+        genl::execute(
+            socket,
+            self.tag_message(Nl80221Message::TriggerScan(request)),
+        )?;
+
+        loop {
+            match events.next_event() {
+                Ok(Nl80221Message::NewScanResults(results)) if results.index == Some(index) => {
+                    break
+                }
+                Ok(Nl80221Message::ScanAborted(results)) if results.index == Some(index) => {
+                    return Err(format_err!("scan was aborted"))
+                }
+                Ok(_) => {}
+                // Notifications this does not know about
+                Err(e) if e.downcast_ref::<DecodeError>().is_some() => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.get_scan(socket, index)
+    }
+
+    /// Have management frames matching `request` passed to `socket`, see [`RegisterFrame`].
+    pub fn register_frame(&self, socket: &Socket, request: RegisterFrame) -> Result<(), Error> {
+        genl::execute(
+            socket,
+            self.tag_message(Nl80221Message::RegisterFrame(request)),
+        )
+    }
+
+    /// Transmit a management frame, returning the cookie its transmission status will carry.
+    ///
+    /// Frames sent with `dont_wait_for_ack` have no status, and no cookie.
+    pub fn transmit_frame(&self, socket: &Socket, frame: Frame) -> Result<Option<Cookie>, Error> {
+        if frame.dont_wait_for_ack.is_some() {
+            genl::execute(socket, self.tag_message(Nl80221Message::Frame(frame)))?;
+            return Ok(None);
+        }
+
+        match genl::request(socket, self.tag_message(Nl80221Message::Frame(frame)))? {
+            Nl80221Message::FrameReceived(FrameInfo {
+                cookie: Some(cookie),
+                ..
+            }) => Ok(Some(cookie)),
+            reply => Err(format_err!("unexpected reply to FRAME: {:?}", reply)),
+        }
+    }
+
+    pub fn start_ap(&self, socket: &Socket, request: StartAp) -> Result<(), Error> {
+        genl::execute(socket, self.tag_message(Nl80221Message::StartAp(request)))
+    }
+
+    pub fn set_beacon(&self, socket: &Socket, request: SetBeacon) -> Result<(), Error> {
+        genl::execute(socket, self.tag_message(Nl80221Message::SetBeacon(request)))
+    }
+
+    pub fn stop_ap(&self, socket: &Socket, index: InterfaceIndex) -> Result<(), Error> {
+        genl::execute(socket, self.tag_message(Nl80221Message::StopAp(index)))
+    }
+
+    /// Describe station `mac` of an interface.
+    pub fn get_station(
+        &self,
+        socket: &Socket,
+        index: InterfaceIndex,
+        mac: MacAddress,
+    ) -> Result<StationInfo, Error> {
+        let query = StationQuery { index, mac };
+        match genl::request(socket, self.tag_message(Nl80221Message::GetStation(query)))? {
+            Nl80221Message::NewStation(station) => Ok(station),
+            reply => Err(format_err!("unexpected reply to GET_STATION: {:?}", reply)),
+        }
+    }
+
+    /// List the stations associated to an interface.
+    pub fn dump_stations(
+        &self,
+        socket: &Socket,
+        index: InterfaceIndex,
+    ) -> Result<Vec<StationInfo>, Error> {
+        let stations = genl::dump(
+            socket,
+            self.tag_message(Nl80221Message::DumpStations(index)),
+        )?
+        .into_iter()
+        .filter_map(|reply| match reply {
+            Nl80221Message::NewStation(station) => Some(station),
+            _ => None,
+        })
+        .collect();
+
+        Ok(stations)
+    }
+
+    pub fn del_station(&self, socket: &Socket, request: DelStation) -> Result<(), Error> {
+        genl::execute(
+            socket,
+            self.tag_message(Nl80221Message::DelStation(request)),
+        )
+    }
+
+    /// Keep the radio of an interface on another channel for a while, and return the cookie
+    /// identifying the period.
+    ///
+    /// Frames can be sent on the channel with a `Frame` at its frequency once the
+    /// `RemainOnChannelStarted` notification with the cookie arrives on the "mlme" group.
+    pub fn remain_on_channel(
+        &self,
+        socket: &Socket,
+        request: RemainOnChannel,
+    ) -> Result<Cookie, Error> {
+        match genl::request(
+            socket,
+            self.tag_message(Nl80221Message::RemainOnChannel(request)),
+        )? {
+            Nl80221Message::RemainOnChannelStarted(reply) => Ok(reply.cookie),
+            reply => Err(format_err!(
+                "unexpected reply to REMAIN_ON_CHANNEL: {:?}",
+                reply
+            )),
+        }
+    }
+
+    pub fn cancel_remain_on_channel(
+        &self,
+        socket: &Socket,
+        index: InterfaceIndex,
+        cookie: Cookie,
+    ) -> Result<(), Error> {
+        let request = CancelRemainOnChannel { index, cookie };
+        genl::execute(
+            socket,
+            self.tag_message(Nl80221Message::CancelRemainOnChannel(request)),
+        )
+    }
+
+    /// Describe the capabilities of radio `wiphy`, using a split dump to get all of them.
+    pub fn get_wiphy(&self, socket: &Socket, wiphy: WiphyIndex) -> Result<Wiphy, Error> {
+        let query = WiphyQuery {
+            wiphy: Some(wiphy),
+            split: Some(SplitWiphyDump),
+        };
+
+        let mut parts = genl::dump(socket, self.tag_message(Nl80221Message::GetWiphy(query)))?
+            .into_iter()
+            .filter_map(|reply| match reply {
+                Nl80221Message::NewWiphy(part) if part.index == wiphy => Some(part),
+                _ => None,
+            });
+
+        let mut description = parts
+            .next()
+            .ok_or_else(|| format_err!("no such wiphy: {}", wiphy.0))?;
+        for part in parts {
+            description.merge(part);
+        }
+
+        Ok(description)
+    }
+}
+
+impl GenlFamily for Nl80221Family {
+    type Message = Nl80221Message;
+
+    fn name(&self) -> FamilyName {
+        self.family.name.clone()
+    }
+
+    fn id(&self) -> FamilyId {
+        self.family.id.clone()
+    }
+}
+
+impl Nl80221Message {
+    fn attribute_kinds(&self) -> Vec<u16> {
+        let mut buffer = vec![0; self.attribute_size()];
+        self.emit_attributes(&mut buffer);
+
+        NlasIterator::new(&buffer)
+            .filter_map(|attribute| attribute.ok().map(|attribute| attribute.kind()))
+            .collect()
+    }
+
+    /// Whether this request is sent with `NLM_F_DUMP`.
+    pub fn is_dump(&self) -> bool {
+        matches!(
+            self,
+            Nl80221Message::GetWiphy(_)
+                | Nl80221Message::DumpInterfaces
+                | Nl80221Message::GetScan(_)
+                | Nl80221Message::DumpStations(_)
+        )
+    }
+
+    /// Check that the kernel's nl80211 policy accepts every attribute of this message, against
+    /// the dump policy for dump requests.
+    pub fn check_policy(&self, policy: &Policy) -> Result<(), Error> {
+        let attributes = self.attribute_kinds();
+        let checked = if self.is_dump() {
+            policy.check_dump(self.command(), &attributes)
+        } else {
+            policy.check(self.command(), &attributes)
+        };
+
+        checked.map_err(|e| format_err!("nl80211 cannot handle {:?}: {}", self, e))
+    }
+}
+
+impl Notifications<Nl80221Family> {
+    /// Wait up to `timeout` for the transmission status of the frame with `cookie`, and return
+    /// whether the frame was acknowledged. A status that does not arrive in time counts as not
+    /// acknowledged.
+    ///
+    /// Needs a subscription to the "mlme" group. Statuses of other frames are dropped, they are
+    /// late answers to earlier attempts. Other notifications stay queued for
+    /// [`next_event`](Notifications::next_event).
+    pub fn wait_for_ack(&mut self, cookie: Cookie, timeout: Duration) -> Result<bool, Error> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let statuses =
+                self.take_pending(|event| matches!(event, Nl80221Message::FrameTxStatus(_)));
+            let acked = statuses.iter().find_map(|event| match event {
+                Nl80221Message::FrameTxStatus(status) if status.cookie == cookie => {
+                    Some(status.ack.is_some())
+                }
+                _ => None,
+            });
+            if let Some(acked) = acked {
+                return Ok(acked);
+            }
+
+            let now = Instant::now();
+            if now >= deadline || !self.receive_within(deadline - now)? {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use netlink_packet_core::NetlinkMessage;
+    use netlink_sys::SocketAddr;
+
+    use super::*;
+    use crate::genl::{GenericBuffer, GenlMessage, GenlPayload, GENL_HDRLEN};
+
+    /// `NL80211_CMD_REMAIN_ON_CHANNEL` notification for 100 ms on 2412 MHz, as sent by
+    /// nl80211_send_remain_on_chan_event() on x86_64, without the netlink header.
+    #[rustfmt::skip]
+    const REMAIN_ON_CHANNEL: &[u8] = &[
+        // NL80211_CMD_REMAIN_ON_CHANNEL, version 1
+        0x37, 0x01, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY 0
+        0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_IFINDEX 3
+        0x08, 0x00, 0x03, 0x00, 0x03, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WDEV 1
+        0x0c, 0x00, 0x99, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY_FREQ 2412
+        0x08, 0x00, 0x26, 0x00, 0x6c, 0x09, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY_CHANNEL_TYPE NL80211_CHAN_NO_HT
+        0x08, 0x00, 0x27, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_COOKIE 0x1234
+        0x0c, 0x00, 0x58, 0x00, 0x34, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_DURATION 100
+        0x08, 0x00, 0x57, 0x00, 0x64, 0x00, 0x00, 0x00,
+    ];
+
+    /// `NL80211_CMD_CANCEL_REMAIN_ON_CHANNEL` notification for the same period, which carries
+    /// no duration.
+    #[rustfmt::skip]
+    const CANCEL_REMAIN_ON_CHANNEL: &[u8] = &[
+        // NL80211_CMD_CANCEL_REMAIN_ON_CHANNEL, version 1
+        0x38, 0x01, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY 0
+        0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_IFINDEX 3
+        0x08, 0x00, 0x03, 0x00, 0x03, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WDEV 1
+        0x0c, 0x00, 0x99, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY_FREQ 2412
+        0x08, 0x00, 0x26, 0x00, 0x6c, 0x09, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY_CHANNEL_TYPE NL80211_CHAN_NO_HT
+        0x08, 0x00, 0x27, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_COOKIE 0x1234
+        0x0c, 0x00, 0x58, 0x00, 0x34, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// `NL80211_CMD_NEW_STATION` notification of a console associating, as sent by
+    /// cfg80211_new_sta() with only the time of association known.
+    #[rustfmt::skip]
+    const NEW_STATION: &[u8] = &[
+        // NL80211_CMD_NEW_STATION, version 1
+        0x13, 0x01, 0x00, 0x00,
+        // NL80211_ATTR_IFINDEX 5
+        0x08, 0x00, 0x03, 0x00, 0x05, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_MAC 00:1f:32:aa:bb:cc
+        0x0a, 0x00, 0x06, 0x00, 0x00, 0x1f, 0x32, 0xaa, 0xbb, 0xcc, 0x00, 0x00,
+        // NL80211_ATTR_GENERATION 7
+        0x08, 0x00, 0x2e, 0x00, 0x07, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_STA_INFO
+        0x18, 0x00, 0x15, 0x80,
+            // NL80211_STA_INFO_CONNECTED_TIME 0
+            0x08, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_STA_INFO_ASSOC_AT_BOOTTIME 0x1234567890
+            0x0c, 0x00, 0x2a, 0x00, 0x90, 0x78, 0x56, 0x34, 0x12, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_IE, the SSID "cecd" of the association request
+        0x0a, 0x00, 0x2a, 0x00, 0x00, 0x04, 0x63, 0x65, 0x63, 0x64, 0x00, 0x00,
+    ];
+
+    /// `NL80211_CMD_DEL_STATION` notification of the same console leaving, with the final
+    /// statistics of the station.
+    #[rustfmt::skip]
+    const DEL_STATION: &[u8] = &[
+        // NL80211_CMD_DEL_STATION, version 1
+        0x14, 0x01, 0x00, 0x00,
+        // NL80211_ATTR_IFINDEX 5
+        0x08, 0x00, 0x03, 0x00, 0x05, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_MAC 00:1f:32:aa:bb:cc
+        0x0a, 0x00, 0x06, 0x00, 0x00, 0x1f, 0x32, 0xaa, 0xbb, 0xcc, 0x00, 0x00,
+        // NL80211_ATTR_GENERATION 8
+        0x08, 0x00, 0x2e, 0x00, 0x08, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_STA_INFO
+        0x68, 0x00, 0x15, 0x80,
+            // NL80211_STA_INFO_CONNECTED_TIME 42
+            0x08, 0x00, 0x10, 0x00, 0x2a, 0x00, 0x00, 0x00,
+            // NL80211_STA_INFO_INACTIVE_TIME 1500
+            0x08, 0x00, 0x01, 0x00, 0xdc, 0x05, 0x00, 0x00,
+            // NL80211_STA_INFO_RX_BYTES, wrapped around
+            0x08, 0x00, 0x02, 0x00, 0x45, 0x23, 0x01, 0x00,
+            // NL80211_STA_INFO_RX_BYTES64 0x100012345
+            0x0c, 0x00, 0x17, 0x00, 0x45, 0x23, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00,
+            // NL80211_STA_INFO_TX_BYTES 2048
+            0x08, 0x00, 0x03, 0x00, 0x00, 0x08, 0x00, 0x00,
+            // NL80211_STA_INFO_TX_BYTES64 2048
+            0x0c, 0x00, 0x18, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_STA_INFO_SIGNAL -40
+            0x05, 0x00, 0x07, 0x00, 0xd8, 0x00, 0x00, 0x00,
+            // NL80211_STA_INFO_SIGNAL_AVG -42
+            0x05, 0x00, 0x0d, 0x00, 0xd6, 0x00, 0x00, 0x00,
+            // NL80211_STA_INFO_RX_PACKETS 100
+            0x08, 0x00, 0x09, 0x00, 0x64, 0x00, 0x00, 0x00,
+            // NL80211_STA_INFO_TX_PACKETS 20
+            0x08, 0x00, 0x0a, 0x00, 0x14, 0x00, 0x00, 0x00,
+            // NL80211_STA_INFO_STA_FLAGS, not parsed
+            0x0c, 0x00, 0x11, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+    ];
+
+    /// `NL80211_CMD_NEW_INTERFACE` reply for a station interface on 2437 MHz, in the layout of
+    /// nl80211_send_iface() on x86_64.
+    #[rustfmt::skip]
+    const NEW_INTERFACE: &[u8] = &[
+        // NL80211_CMD_NEW_INTERFACE, version 1
+        0x07, 0x01, 0x00, 0x00,
+        // NL80211_ATTR_IFINDEX 3
+        0x08, 0x00, 0x03, 0x00, 0x03, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_IFNAME "wlan0"
+        0x0a, 0x00, 0x04, 0x00, 0x77, 0x6c, 0x61, 0x6e, 0x30, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_IFTYPE NL80211_IFTYPE_STATION
+        0x08, 0x00, 0x05, 0x00, 0x02, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY 0
+        0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WDEV 1
+        0x0c, 0x00, 0x99, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_MAC 02:00:00:12:34:56
+        0x0a, 0x00, 0x06, 0x00, 0x02, 0x00, 0x00, 0x12, 0x34, 0x56, 0x00, 0x00,
+        // NL80211_ATTR_GENERATION 5
+        0x08, 0x00, 0x2e, 0x00, 0x05, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_4ADDR 0, skipped
+        0x05, 0x00, 0x53, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY_FREQ 2437
+        0x08, 0x00, 0x26, 0x00, 0x85, 0x09, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY_FREQ_OFFSET 0, skipped
+        0x08, 0x00, 0x22, 0x01, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_CHANNEL_WIDTH NL80211_CHAN_WIDTH_20_NOHT
+        0x08, 0x00, 0x9f, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY_CHANNEL_TYPE NL80211_CHAN_NO_HT
+        0x08, 0x00, 0x27, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_CENTER_FREQ1 2437
+        0x08, 0x00, 0xa0, 0x00, 0x85, 0x09, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY_TX_POWER_LEVEL 2000 mBm, skipped
+        0x08, 0x00, 0x62, 0x00, 0xd0, 0x07, 0x00, 0x00,
+    ];
+
+    /// Entry of the `NL80211_CMD_GET_SCAN` dump for a relay on channel 1, in the layout of
+    /// nl80211_send_bss() on x86_64.
+    #[rustfmt::skip]
+    const NEW_SCAN_RESULTS: &[u8] = &[
+        // NL80211_CMD_NEW_SCAN_RESULTS, version 1
+        0x22, 0x01, 0x00, 0x00,
+        // NL80211_ATTR_GENERATION 12
+        0x08, 0x00, 0x2e, 0x00, 0x0c, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_IFINDEX 3
+        0x08, 0x00, 0x03, 0x00, 0x03, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WDEV 1
+        0x0c, 0x00, 0x99, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_BSS
+        0x68, 0x00, 0x2f, 0x00,
+            // NL80211_BSS_BSSID 02:00:00:ab:cd:ef
+            0x0a, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0xab, 0xcd, 0xef, 0x00, 0x00,
+            // NL80211_BSS_TSF 0x123456789a
+            0x0c, 0x00, 0x03, 0x00, 0x9a, 0x78, 0x56, 0x34, 0x12, 0x00, 0x00, 0x00,
+            // NL80211_BSS_INFORMATION_ELEMENTS, SSID "cecd" and channel 1
+            0x0d, 0x00, 0x06, 0x00, 0x00, 0x04, 0x63, 0x65, 0x63, 0x64, 0x03, 0x01,
+            0x01, 0x00, 0x00, 0x00,
+            // NL80211_BSS_BEACON_INTERVAL 100
+            0x06, 0x00, 0x04, 0x00, 0x64, 0x00, 0x00, 0x00,
+            // NL80211_BSS_CAPABILITY ESS
+            0x06, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x00,
+            // NL80211_BSS_FREQUENCY 2412
+            0x08, 0x00, 0x02, 0x00, 0x6c, 0x09, 0x00, 0x00,
+            // NL80211_BSS_FREQUENCY_OFFSET 0, skipped
+            0x08, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_BSS_SEEN_MS_AGO 40
+            0x08, 0x00, 0x0a, 0x00, 0x28, 0x00, 0x00, 0x00,
+            // NL80211_BSS_LAST_SEEN_BOOTTIME, skipped
+            0x0c, 0x00, 0x0f, 0x00, 0x00, 0xf2, 0x05, 0x2a, 0x01, 0x00, 0x00, 0x00,
+            // NL80211_BSS_SIGNAL_MBM -4500
+            0x08, 0x00, 0x07, 0x00, 0x6c, 0xee, 0xff, 0xff,
+    ];
+
+    /// `NL80211_CMD_FRAME` notification of a probe request received on a registration, in the
+    /// layout of nl80211_send_mgmt() on x86_64.
+    #[rustfmt::skip]
+    const FRAME: &[u8] = &[
+        // NL80211_CMD_FRAME, version 1
+        0x3b, 0x01, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY 0
+        0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_IFINDEX 5
+        0x08, 0x00, 0x03, 0x00, 0x05, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WDEV 2
+        0x0c, 0x00, 0x99, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // NL80211_ATTR_WIPHY_FREQ 2412
+        0x08, 0x00, 0x26, 0x00, 0x6c, 0x09, 0x00, 0x00,
+        // NL80211_ATTR_RX_SIGNAL_DBM -52
+        0x08, 0x00, 0x97, 0x00, 0xcc, 0xff, 0xff, 0xff,
+        // NL80211_ATTR_FRAME, a probe request for "cecd" from 00:1f:32:aa:bb:cc
+        0x22, 0x00, 0x33, 0x00, 0x40, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0x00, 0x1f, 0x32, 0xaa, 0xbb, 0xcc, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0x10, 0x00, 0x00, 0x04, 0x63, 0x65, 0x63, 0x64, 0x00, 0x00,
+        // NL80211_ATTR_RXMGMT_FLAGS 0, skipped
+        0x08, 0x00, 0xbc, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// Reply to transmitting a frame with `NL80211_CMD_FRAME`, as sent by nl80211_tx_mgmt().
+    #[rustfmt::skip]
+    const FRAME_COOKIE: &[u8] = &[
+        // NL80211_CMD_FRAME, version 1
+        0x3b, 0x01, 0x00, 0x00,
+        // NL80211_ATTR_COOKIE 0x2a
+        0x0c, 0x00, 0x58, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// Split `NL80211_CMD_NEW_WIPHY` dump of a radio in the layout of nl80211_send_wiphy(), one
+    /// message per part and without the netlink headers. The kernel starts these nests without
+    /// `NLA_F_NESTED`, and continues the channels of a band in the next message.
+    #[rustfmt::skip]
+    const NEW_WIPHY: &[&[u8]] = &[
+        // General capabilities
+        &[
+            // NL80211_CMD_NEW_WIPHY, version 1
+            0x03, 0x01, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY 0
+            0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY_NAME "phy0"
+            0x09, 0x00, 0x02, 0x00, 0x70, 0x68, 0x79, 0x30, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_GENERATION 3
+            0x08, 0x00, 0x2e, 0x00, 0x03, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_MAX_NUM_SCAN_SSIDS 4
+            0x05, 0x00, 0x2b, 0x00, 0x04, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_CIPHER_SUITES CCMP
+            0x08, 0x00, 0x39, 0x00, 0x04, 0xac, 0x0f, 0x00,
+            // NL80211_ATTR_SUPPORTED_IFTYPES
+            0x10, 0x00, 0x20, 0x00,
+                // NL80211_IFTYPE_STATION
+                0x04, 0x00, 0x02, 0x00,
+                // NL80211_IFTYPE_AP
+                0x04, 0x00, 0x03, 0x00,
+                // NL80211_IFTYPE_MONITOR
+                0x04, 0x00, 0x06, 0x00,
+        ],
+        // The first channels of the 2.4 GHz band
+        &[
+            // NL80211_CMD_NEW_WIPHY, version 1
+            0x03, 0x01, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY 0
+            0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY_NAME "phy0"
+            0x09, 0x00, 0x02, 0x00, 0x70, 0x68, 0x79, 0x30, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_GENERATION 3
+            0x08, 0x00, 0x2e, 0x00, 0x03, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY_BANDS
+            0x3c, 0x00, 0x16, 0x00,
+                // NL80211_BAND_2GHZ
+                0x38, 0x00, 0x00, 0x00,
+                    // NL80211_BAND_ATTR_HT_CAPA, skipped
+                    0x06, 0x00, 0x04, 0x00, 0x6e, 0x11, 0x00, 0x00,
+                    // NL80211_BAND_ATTR_FREQS
+                    0x2c, 0x00, 0x01, 0x00,
+                        // 0: 2412 MHz at 20 dBm
+                        0x14, 0x00, 0x00, 0x00,
+                            0x08, 0x00, 0x01, 0x00, 0x6c, 0x09, 0x00, 0x00,
+                            0x08, 0x00, 0x06, 0x00, 0xd0, 0x07, 0x00, 0x00,
+                        // 1: 2437 MHz at 20 dBm
+                        0x14, 0x00, 0x01, 0x00,
+                            0x08, 0x00, 0x01, 0x00, 0x85, 0x09, 0x00, 0x00,
+                            0x08, 0x00, 0x06, 0x00, 0xd0, 0x07, 0x00, 0x00,
+        ],
+        // The rest of the 2.4 GHz band
+        &[
+            // NL80211_CMD_NEW_WIPHY, version 1
+            0x03, 0x01, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY 0
+            0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY_NAME "phy0"
+            0x09, 0x00, 0x02, 0x00, 0x70, 0x68, 0x79, 0x30, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_GENERATION 3
+            0x08, 0x00, 0x2e, 0x00, 0x03, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY_BANDS
+            0x34, 0x00, 0x16, 0x00,
+                // NL80211_BAND_2GHZ
+                0x30, 0x00, 0x00, 0x00,
+                    // NL80211_BAND_ATTR_FREQS
+                    0x2c, 0x00, 0x01, 0x00,
+                        // 2: 2462 MHz, no initiating radiation
+                        0x18, 0x00, 0x02, 0x00,
+                            0x08, 0x00, 0x01, 0x00, 0x9e, 0x09, 0x00, 0x00,
+                            0x04, 0x00, 0x03, 0x00,
+                            0x08, 0x00, 0x06, 0x00, 0xd0, 0x07, 0x00, 0x00,
+                        // 3: 2484 MHz, disabled
+                        0x10, 0x00, 0x03, 0x00,
+                            0x08, 0x00, 0x01, 0x00, 0xb4, 0x09, 0x00, 0x00,
+                            0x04, 0x00, 0x02, 0x00,
+        ],
+        // Commands and management frames
+        &[
+            // NL80211_CMD_NEW_WIPHY, version 1
+            0x03, 0x01, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY 0
+            0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY_NAME "phy0"
+            0x09, 0x00, 0x02, 0x00, 0x70, 0x68, 0x79, 0x30, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_GENERATION 3
+            0x08, 0x00, 0x2e, 0x00, 0x03, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_SUPPORTED_COMMANDS
+            0x24, 0x00, 0x32, 0x00,
+                // 1: NL80211_CMD_NEW_INTERFACE
+                0x08, 0x00, 0x01, 0x00, 0x07, 0x00, 0x00, 0x00,
+                // 2: NL80211_CMD_START_AP
+                0x08, 0x00, 0x02, 0x00, 0x0f, 0x00, 0x00, 0x00,
+                // 3: NL80211_CMD_REGISTER_FRAME
+                0x08, 0x00, 0x03, 0x00, 0x3a, 0x00, 0x00, 0x00,
+                // 4: NL80211_CMD_FRAME
+                0x08, 0x00, 0x04, 0x00, 0x3b, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_TX_FRAME_TYPES
+            0x18, 0x00, 0x63, 0x00,
+                // NL80211_IFTYPE_AP: probe responses and action frames
+                0x14, 0x00, 0x03, 0x00,
+                    0x06, 0x00, 0x65, 0x00, 0x50, 0x00, 0x00, 0x00,
+                    0x06, 0x00, 0x65, 0x00, 0xd0, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_RX_FRAME_TYPES
+            0x18, 0x00, 0x64, 0x00,
+                // NL80211_IFTYPE_AP: probe requests and action frames
+                0x14, 0x00, 0x03, 0x00,
+                    0x06, 0x00, 0x65, 0x00, 0x40, 0x00, 0x00, 0x00,
+                    0x06, 0x00, 0x65, 0x00, 0xd0, 0x00, 0x00, 0x00,
+        ],
+        // Interface combinations
+        &[
+            // NL80211_CMD_NEW_WIPHY, version 1
+            0x03, 0x01, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY 0
+            0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY_NAME "phy0"
+            0x09, 0x00, 0x02, 0x00, 0x70, 0x68, 0x79, 0x30, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_GENERATION 3
+            0x08, 0x00, 0x2e, 0x00, 0x03, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_INTERFACE_COMBINATIONS
+            0x48, 0x00, 0x78, 0x00,
+                // 1: a station and an access point on one channel
+                0x44, 0x00, 0x01, 0x00,
+                    // NL80211_IFACE_COMB_LIMITS
+                    0x2c, 0x00, 0x01, 0x00,
+                        // 1: one station
+                        0x14, 0x00, 0x01, 0x00,
+                            // NL80211_IFACE_LIMIT_MAX 1
+                            0x08, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00,
+                            // NL80211_IFACE_LIMIT_TYPES
+                            0x08, 0x00, 0x02, 0x00,
+                                0x04, 0x00, 0x02, 0x00,
+                        // 2: one access point
+                        0x14, 0x00, 0x02, 0x00,
+                            // NL80211_IFACE_LIMIT_MAX 1
+                            0x08, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00,
+                            // NL80211_IFACE_LIMIT_TYPES
+                            0x08, 0x00, 0x02, 0x00,
+                                0x04, 0x00, 0x03, 0x00,
+                    // NL80211_IFACE_COMB_MAXNUM 2
+                    0x08, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00,
+                    // NL80211_IFACE_COMB_STA_AP_BI_MATCH
+                    0x04, 0x00, 0x03, 0x00,
+                    // NL80211_IFACE_COMB_NUM_CHANNELS 1
+                    0x08, 0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00,
+        ],
+    ];
+
+    fn parse(payload: &[u8]) -> Nl80221Message {
+        let buffer = GenericBuffer::new_checked(payload).unwrap();
+        <Nl80221Message as GenlPayload>::parse(&buffer).unwrap()
+    }
+
+    fn emit(message: &Nl80221Message) -> Vec<u8> {
+        let mut payload = vec![0; GENL_HDRLEN + message.attribute_size()];
+        let mut buffer = GenericBuffer::new_checked(&mut payload[..]).unwrap();
+        buffer.set_command(message.command());
+        buffer.set_version(message.version());
+        message.emit_attributes(buffer.attributes_mut());
+        payload
+    }
+
+    fn remain_on_channel_info(duration: Option<u32>) -> RemainOnChannelInfo {
+        RemainOnChannelInfo {
+            wiphy: Some(WiphyIndex(0)),
+            index: Some(InterfaceIndex(3)),
+            wdev: Some(WirelessDevice(1)),
+            frequency: Some(Frequency(2412)),
+            channel_type: Some(ChannelType::NoHt),
+            cookie: Cookie(0x1234),
+            duration: duration.map(DurationMs),
+        }
+    }
+
+    #[test]
+    fn remain_on_channel_request() {
+        let request =
+            Nl80221Message::RemainOnChannel(RemainOnChannel::new(InterfaceIndex(3), 2412, 100));
+
+        #[rustfmt::skip]
+        let expected = [
+            // NL80211_CMD_REMAIN_ON_CHANNEL, version 0
+            0x37, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_IFINDEX 3
+            0x08, 0x00, 0x03, 0x00, 0x03, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY_FREQ 2412
+            0x08, 0x00, 0x26, 0x00, 0x6c, 0x09, 0x00, 0x00,
+            // NL80211_ATTR_DURATION 100
+            0x08, 0x00, 0x57, 0x00, 0x64, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(emit(&request), expected);
+    }
+
+    #[test]
+    fn remain_on_channel_started() {
+        assert_eq!(
+            parse(REMAIN_ON_CHANNEL),
+            Nl80221Message::RemainOnChannelStarted(remain_on_channel_info(Some(100)))
+        );
+    }
+
+    #[test]
+    fn remain_on_channel_ended() {
+        assert_eq!(
+            parse(CANCEL_REMAIN_ON_CHANNEL),
+            Nl80221Message::RemainOnChannelEnded(remain_on_channel_info(None))
+        );
+    }
+
+    #[test]
+    fn new_station() {
+        let station = match parse(NEW_STATION) {
+            Nl80221Message::NewStation(station) => station,
+            other => panic!("expected NewStation, got {:?}", other),
+        };
+
+        assert_eq!(
+            station,
+            StationInfo {
+                index: Some(InterfaceIndex(5)),
+                mac: MacAddress([0x00, 0x1f, 0x32, 0xaa, 0xbb, 0xcc]),
+                generation: Some(Generation(7)),
+                stats: Some(StaInfo(StationStats {
+                    connected_time: Some(ConnectedTime(0)),
+                    inactive_time: None,
+                    associated_at: Some(AssociatedAt(0x12_3456_7890)),
+                    rx_bytes: None,
+                    rx_bytes64: None,
+                    tx_bytes: None,
+                    tx_bytes64: None,
+                    rx_packets: None,
+                    tx_packets: None,
+                    tx_retries: None,
+                    tx_failed: None,
+                    signal: None,
+                    signal_avg: None,
+                })),
+                ies: Some(InformationElements(b"\x00\x04cecd".to_vec())),
+            }
+        );
+    }
+
+    #[test]
+    fn station_deleted() {
+        let station = match parse(DEL_STATION) {
+            Nl80221Message::StationDeleted(station) => station,
+            other => panic!("expected StationDeleted, got {:?}", other),
+        };
+        assert_eq!(station.index, Some(InterfaceIndex(5)));
+        assert_eq!(station.mac(), [0x00, 0x1f, 0x32, 0xaa, 0xbb, 0xcc]);
+        assert_eq!(station.ies, None);
+
+        let stats = station.stats().unwrap();
+        assert_eq!(stats.connected_secs(), Some(42));
+        assert_eq!(stats.inactive_time, Some(InactiveTime(1500)));
+        // The 64-bit counter wins over the wrapped 32-bit one
+        assert_eq!(stats.rx_bytes(), Some(0x1_0001_2345));
+        assert_eq!(stats.tx_bytes(), Some(2048));
+        assert_eq!(stats.rx_packets(), Some(100));
+        assert_eq!(stats.tx_packets(), Some(20));
+        assert_eq!(stats.dbm(), Some(-40));
+        assert_eq!(stats.signal_avg, Some(StationSignalAvg(-42)));
+    }
+
+    #[test]
+    fn set_beacon_request() {
+        let request = Nl80221Message::SetBeacon(SetBeacon {
+            index: InterfaceIndex(4),
+            beacon_head: Some(BeaconHead::new(vec![0x80, 0x00, 0x00, 0x00, 0xff])),
+            beacon_tail: Some(BeaconTail::new(vec![0xdd, 0x00])),
+            probe_response: None,
+        });
+
+        #[rustfmt::skip]
+        let expected = [
+            // NL80211_CMD_SET_BEACON, version 0
+            0x0e, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_IFINDEX 4
+            0x08, 0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_BEACON_HEAD
+            0x09, 0x00, 0x0e, 0x00, 0x80, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_BEACON_TAIL, an empty vendor element
+            0x06, 0x00, 0x0f, 0x00, 0xdd, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(emit(&request), expected);
+    }
+
+    /// Notifications of nl80211 on none of its multicast groups, so only what the test sends
+    /// to the socket arrives.
+    fn notifications() -> Notifications<Nl80221Family> {
+        let nl80211 = Nl80221Family {
+            family: NewFamily {
+                id: FamilyId::from(0x1c),
+                name: FamilyName::new(NL80211_FAMILY_NAME),
+                version: Default::default(),
+                header_size: Default::default(),
+                max_attributes: Default::default(),
+                operations: Default::default(),
+                multicast_groups: Default::default(),
+            },
+        };
+        nl80211.notifications(&[]).unwrap()
+    }
+
+    /// Send `messages` to the socket of `events` in a single datagram, as the kernel would.
+    fn deliver(events: &Notifications<Nl80221Family>, messages: Vec<Nl80221Message>) {
+        let mut datagram = Vec::new();
+        for message in messages {
+            let mut packet = NetlinkMessage::from(GenlMessage::<Nl80221Family>::new(
+                FamilyId::from(0x1c),
+                message,
+            ));
+            packet.finalize();
+            let start = datagram.len();
+            datagram.resize(start + packet.header.length as usize, 0);
+            packet.serialize(&mut datagram[start..]);
+        }
+
+        let mut address = SocketAddr::new(0, 0);
+        events.socket().get_address(&mut address).unwrap();
+        events.socket().send_to(&datagram, &address, 0).unwrap();
+    }
+
+    fn tx_status(cookie: u64, ack: bool) -> Nl80221Message {
+        Nl80221Message::FrameTxStatus(FrameTxStatus {
+            wiphy: None,
+            index: Some(InterfaceIndex(4)),
+            wdev: None,
+            frame: None,
+            cookie: Cookie(cookie),
+            ack: if ack { Some(Ack) } else { None },
+        })
+    }
+
+    #[test]
+    fn wait_for_ack_gives_up() {
+        let mut events = notifications();
+        deliver(
+            &events,
+            vec![
+                tx_status(1, true),
+                Nl80221Message::RemainOnChannelEnded(remain_on_channel_info(None)),
+                tx_status(2, true),
+            ],
+        );
+
+        let started = Instant::now();
+        let acked = events.wait_for_ack(Cookie(3), Duration::from_millis(20));
+        assert!(!acked.unwrap());
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        // The stale statuses are gone, the other notification is left
+        assert_eq!(
+            events.take_pending(|_| true),
+            [Nl80221Message::RemainOnChannelEnded(
+                remain_on_channel_info(None)
+            )]
+        );
+    }
+
+    #[test]
+    fn wait_for_ack_finds_status() {
+        let mut events = notifications();
+        deliver(&events, vec![tx_status(1, false), tx_status(2, true)]);
+
+        let acked = events.wait_for_ack(Cookie(2), Duration::from_secs(1));
+        assert!(acked.unwrap());
+        assert!(events.take_pending(|_| true).is_empty());
+
+        // Statuses that already arrived are found without waiting
+        deliver(&events, vec![tx_status(3, false)]);
+        assert!(events.receive_within(Duration::from_secs(1)).unwrap());
+        let acked = events.wait_for_ack(Cookie(3), Duration::from_millis(0));
+        assert!(!acked.unwrap());
+    }
+
+    #[test]
+    fn next_event_in_order() {
+        let mut events = notifications();
+        deliver(
+            &events,
+            vec![
+                Nl80221Message::RemainOnChannelEnded(remain_on_channel_info(None)),
+                Nl80221Message::RemainOnChannelEnded(remain_on_channel_info(Some(100))),
+            ],
+        );
+
+        let received = events.by_ref().take(2).collect::<Result<Vec<_>, _>>();
+        assert_eq!(
+            received.unwrap(),
+            [
+                Nl80221Message::RemainOnChannelEnded(remain_on_channel_info(None)),
+                Nl80221Message::RemainOnChannelEnded(remain_on_channel_info(Some(100))),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_wiphy() {
+        let mut parts = NEW_WIPHY.iter().map(|payload| match parse(payload) {
+            Nl80221Message::NewWiphy(wiphy) => wiphy,
+            other => panic!("expected NewWiphy, got {:?}", other),
+        });
+        let mut wiphy = parts.next().unwrap();
+        for part in parts {
+            wiphy.merge(part);
+        }
+
+        assert_eq!(wiphy.index, WiphyIndex(0));
+        assert_eq!(wiphy.name.as_str(), "phy0");
+        assert_eq!(wiphy.generation, Some(Generation(3)));
+        assert_eq!(wiphy.max_scan_ssids, Some(MaxScanSsids(4)));
+        assert_eq!(wiphy.cipher_suites, CipherSuites(vec![0x000fac04]));
+        assert!(wiphy.iftypes.contains(InterfaceType::Ap));
+        assert!(wiphy.iftypes.contains(InterfaceType::Monitor));
+        assert!(!wiphy.iftypes.contains(InterfaceType::MeshPoint));
+
+        // Both halves of the band end up in one
+        let bands = wiphy.bands.iter().collect::<Vec<_>>();
+        assert_eq!(bands.len(), 1);
+        assert_eq!(bands[0].0, Band::TwoGhz);
+        let channels = bands[0].1.channels.iter().map(Channel::mhz);
+        assert_eq!(channels.collect::<Vec<_>>(), vec![2412, 2437, 2462, 2484]);
+
+        let channel = wiphy.channel(2412).unwrap();
+        assert_eq!(channel.max_tx_power, Some(ChannelMaxTxPower(2000)));
+        assert_eq!((channel.disabled, channel.no_ir), (None, None));
+        assert_eq!(wiphy.channel(2462).unwrap().no_ir, Some(ChannelNoIr));
+        assert_eq!(wiphy.channel(2484).unwrap().disabled, Some(ChannelDisabled));
+        assert!(wiphy.channel(5180).is_none());
+
+        // Emitted maps are marked as nested, which parsing does not depend on
+        let mut buffer = vec![0; wiphy.bands.buffer_len()];
+        wiphy.bands.emit(&mut buffer);
+        assert_eq!(buffer[2..4], [0x16, 0x80]);
+        assert_eq!(
+            BandList::parse(&NlaBuffer::new(&buffer)).unwrap(),
+            wiphy.bands
+        );
+
+        assert!(wiphy.commands.contains(NL80211_CMD_START_AP));
+        assert!(wiphy.commands.contains(NL80211_CMD_FRAME));
+        assert!(!wiphy.commands.contains(NL80211_CMD_SET_BEACON));
+        assert!(wiphy.tx_frame_types.contains(InterfaceType::Ap, 0x00d0));
+        assert!(!wiphy
+            .tx_frame_types
+            .contains(InterfaceType::Station, 0x00d0));
+        assert!(wiphy.rx_frame_types.contains(InterfaceType::Ap, 0x0040));
+
+        let combinations = &wiphy.interface_combinations.combinations;
+        assert_eq!(combinations.len(), 1);
+        assert_eq!(combinations[0].max_interfaces, CombinationMaxInterfaces(2));
+        assert_eq!(combinations[0].channels, CombinationChannels(1));
+        assert!(combinations[0].beacon_interval_match.is_some());
+        let limits = &combinations[0].limits.limits;
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits[1].max, LimitMaxInterfaces(1));
+        assert_eq!(limits[1].iftypes.iftypes, vec![(InterfaceType::Ap, ())]);
+    }
+
+    #[test]
+    fn new_interface() {
+        let interface = match parse(NEW_INTERFACE) {
+            Nl80221Message::NewInterface(interface) => interface,
+            other => panic!("expected NewInterface, got {:?}", other),
+        };
+
+        assert_eq!(
+            interface,
+            Interface {
+                index: Some(InterfaceIndex(3)),
+                name: Some(InterfaceName::new("wlan0")),
+                wiphy: WiphyIndex(0),
+                iftype: InterfaceType::Station,
+                wdev: Some(WirelessDevice(1)),
+                mac: Some(MacAddress([0x02, 0x00, 0x00, 0x12, 0x34, 0x56])),
+                generation: Some(Generation(5)),
+                frequency: Some(Frequency(2437)),
+                channel_width: Some(ChannelWidth::Width20NoHt),
+                channel_type: Some(ChannelType::NoHt),
+                center_frequency1: Some(CenterFrequency1(2437)),
+                center_frequency2: None,
+            }
+        );
+    }
+
+    #[test]
+    fn new_monitor_interface_request() {
+        let request = Nl80221Message::CreateInterface(NewInterface {
+            wiphy: WiphyIndex(0),
+            name: InterfaceName::new("phy0mon"),
+            iftype: InterfaceType::Monitor,
+            mac: None,
+            flags: Some(MonitorFlags::new(vec![
+                MonitorFlag::OtherBss,
+                MonitorFlag::Control,
+            ])),
+        });
+
+        #[rustfmt::skip]
+        let expected = [
+            // NL80211_CMD_NEW_INTERFACE, version 0
+            0x07, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_WIPHY 0
+            0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_IFNAME "phy0mon"
+            0x0c, 0x00, 0x04, 0x00, 0x70, 0x68, 0x79, 0x30, 0x6d, 0x6f, 0x6e, 0x00,
+            // NL80211_ATTR_IFTYPE NL80211_IFTYPE_MONITOR
+            0x08, 0x00, 0x05, 0x00, 0x06, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_MNTR_FLAGS, nested
+            0x0c, 0x00, 0x17, 0x80,
+                // NL80211_MNTR_FLAG_OTHER_BSS
+                0x04, 0x00, 0x04, 0x00,
+                // NL80211_MNTR_FLAG_CONTROL
+                0x04, 0x00, 0x03, 0x00,
+        ];
+        assert_eq!(emit(&request), expected);
+    }
+
+    #[test]
+    fn del_interface_request() {
+        #[rustfmt::skip]
+        let expected = [
+            // NL80211_CMD_DEL_INTERFACE, version 0
+            0x08, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_IFINDEX 7
+            0x08, 0x00, 0x03, 0x00, 0x07, 0x00, 0x00, 0x00,
+        ];
+        let request = Nl80221Message::DelInterface(InterfaceIndex(7));
+        assert_eq!(emit(&request), expected);
+    }
+
+    #[test]
+    fn trigger_scan_request() {
+        let request = Nl80221Message::TriggerScan(TriggerScan {
+            index: InterfaceIndex(3),
+            ssids: Some(ScanSsids::new(vec![Vec::new(), b"cecd".to_vec()])),
+            frequencies: Some(ScanFrequencies::new(vec![2412, 2437, 2462])),
+            extra_ies: None,
+        });
+
+        #[rustfmt::skip]
+        let expected = [
+            // NL80211_CMD_TRIGGER_SCAN, version 0
+            0x21, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_IFINDEX 3
+            0x08, 0x00, 0x03, 0x00, 0x03, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_SCAN_SSIDS, nested
+            0x10, 0x00, 0x2d, 0x80,
+                // 1: the wildcard SSID
+                0x04, 0x00, 0x01, 0x00,
+                // 2: "cecd"
+                0x08, 0x00, 0x02, 0x00, 0x63, 0x65, 0x63, 0x64,
+            // NL80211_ATTR_SCAN_FREQUENCIES, nested
+            0x1c, 0x00, 0x2c, 0x80,
+                // 1: 2412 MHz
+                0x08, 0x00, 0x01, 0x00, 0x6c, 0x09, 0x00, 0x00,
+                // 2: 2437 MHz
+                0x08, 0x00, 0x02, 0x00, 0x85, 0x09, 0x00, 0x00,
+                // 3: 2462 MHz
+                0x08, 0x00, 0x03, 0x00, 0x9e, 0x09, 0x00, 0x00,
+        ];
+        assert_eq!(emit(&request), expected);
+    }
+
+    #[test]
+    fn scan_result() {
+        let results = match parse(NEW_SCAN_RESULTS) {
+            Nl80221Message::NewScanResults(results) => results,
+            other => panic!("expected NewScanResults, got {:?}", other),
+        };
+        assert_eq!(results.generation, Some(Generation(12)));
+        assert_eq!(results.index, Some(InterfaceIndex(3)));
+        assert_eq!(results.wdev, Some(WirelessDevice(1)));
+        assert_eq!(
+            (results.ssids.as_ref(), results.frequencies.as_ref()),
+            (None, None)
+        );
+
+        let bss = results.bss.unwrap().0;
+        assert_eq!(bss.bssid(), [0x02, 0x00, 0x00, 0xab, 0xcd, 0xef]);
+        assert_eq!(bss.tsf, Some(Tsf(0x12_3456_789a)));
+        assert_eq!(
+            bss.ies,
+            Some(BssInformationElements(vec![
+                0x00, 0x04, 0x63, 0x65, 0x63, 0x64, 0x03, 0x01, 0x01,
+            ]))
+        );
+        assert_eq!(bss.beacon_ies, None);
+        assert_eq!(bss.beacon_interval, Some(BeaconInterval(100)));
+        assert_eq!(bss.capability, Some(Capability(1)));
+        assert_eq!(bss.mhz(), 2412);
+        assert_eq!(bss.seen_ms_ago, Some(SeenMsAgo(40)));
+        assert_eq!(bss.signal_mbm, Some(SignalMbm(-4500)));
+        assert_eq!(bss.dbm(), Some(-45.0));
+        assert_eq!(bss.signal_unspecified, None);
+    }
+
+    #[test]
+    fn register_frame_request() {
+        let request = Nl80221Message::RegisterFrame(RegisterFrame {
+            index: InterfaceIndex(5),
+            frame_type: FrameType::new(0x00d0),
+            match_bytes: FrameMatch::new(vec![0x7f, 0x00, 0x1f, 0x32]),
+        });
+
+        #[rustfmt::skip]
+        let expected = [
+            // NL80211_CMD_REGISTER_FRAME, version 0
+            0x3a, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_IFINDEX 5
+            0x08, 0x00, 0x03, 0x00, 0x05, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_FRAME_TYPE, action frames
+            0x06, 0x00, 0x65, 0x00, 0xd0, 0x00, 0x00, 0x00,
+            // NL80211_ATTR_FRAME_MATCH, the Nintendo vendor-specific category
+            0x08, 0x00, 0x5b, 0x00, 0x7f, 0x00, 0x1f, 0x32,
+        ];
+        assert_eq!(emit(&request), expected);
+    }
+
+    #[test]
+    fn frame_received() {
+        let frame = match parse(FRAME) {
+            Nl80221Message::FrameReceived(frame) => frame,
+            other => panic!("expected FrameReceived, got {:?}", other),
+        };
+        assert_eq!(frame.wiphy, Some(WiphyIndex(0)));
+        assert_eq!(frame.index, Some(InterfaceIndex(5)));
+        assert_eq!(frame.wdev, Some(WirelessDevice(2)));
+        assert_eq!(frame.mhz(), Some(2412));
+        assert_eq!(frame.dbm(), Some(-52));
+        assert_eq!(frame.cookie, None);
+
+        let bytes = frame.frame.as_ref().unwrap().as_bytes();
+        assert_eq!(bytes.len(), 30);
+        assert_eq!(bytes[0], 0x40);
+        assert_eq!(bytes[24..], *b"\x00\x04cecd");
+    }
+
+    #[test]
+    fn frame_transmitted() {
+        let reply = match parse(FRAME_COOKIE) {
+            Nl80221Message::FrameReceived(reply) => reply,
+            other => panic!("expected FrameReceived, got {:?}", other),
+        };
+        assert_eq!(reply.cookie, Some(Cookie(0x2a)));
+        assert_eq!(
+            (reply.frame, reply.frequency, reply.signal),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn changes_undone_in_reverse() {
+        let mut changes = InterfaceChanges::default();
+        let channel = SetChannel::no_ht(InterfaceIndex(3), 2437);
+        let iftype = SetInterface {
+            index: InterfaceIndex(3),
+            iftype: InterfaceType::Station,
+        };
+        changes.record(Nl80221Message::SetChannel(channel.clone()));
+        changes.record(Nl80221Message::SetInterface(iftype.clone()));
+
+        assert_eq!(
+            changes.undo_requests().cloned().collect::<Vec<_>>(),
+            vec![
+                Nl80221Message::SetInterface(iftype),
+                Nl80221Message::SetChannel(channel),
+            ]
+        );
+        assert_eq!(InterfaceChanges::default().undo_requests().count(), 0);
     }
 }